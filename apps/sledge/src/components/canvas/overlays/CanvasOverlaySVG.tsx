@@ -2,6 +2,7 @@ import { RGBAToHex, TileIndex } from '@sledge/anvil';
 import { mask_to_path } from '@sledge/wasm';
 import createRAF, { targetFPS } from '@solid-primitives/raf';
 import { Component, createEffect, createSignal, For, JSX, onMount, Show } from 'solid-js';
+import { logSystemWarn } from '~/features/log/service';
 import { floatingMoveManager } from '~/features/selection/FloatingMoveManager';
 import { selectionManager } from '~/features/selection/SelectionAreaManager';
 import { getSelectionOffset } from '~/features/selection/SelectionOperator';
@@ -75,8 +76,14 @@ const CanvasOverlaySVG: Component = () => {
     const { width, height } = canvasStore.canvas;
     const offset = getSelectionOffset();
     const mask = selectionManager.getCombinedMask();
-    const pathString = mask_to_path(mask, width, height, offset.x, offset.y);
-    setPathCmdList(PathCmdList.parse(pathString));
+    try {
+      const pathString = mask_to_path(mask, width, height, offset.x, offset.y);
+      setPathCmdList(PathCmdList.parse(pathString));
+    } catch (error) {
+      // キャンバスのリサイズ直後などマスクとサイズが合わない間は輪郭を出さない
+      logSystemWarn('Failed to update selection outline.', { label: 'CanvasOverlaySVG', details: [error] });
+      setPathCmdList(new PathCmdList([]));
+    }
   };
 
   const [patternOffset, setPatternOffset] = createSignal(0);
@@ -142,9 +149,15 @@ const CanvasOverlaySVG: Component = () => {
     const key = `${tool}-${size}-${shape}`;
     if (key === cachedKey && cachedLocalPath) return;
     const { mask, width, height } = getDrawnPixelMask(size, shape);
-    const localPath = mask_to_path(mask, width, height, 0, 0);
-    cachedLocalPath = PathCmdList.parse(localPath);
-    cachedKey = key;
+    try {
+      const localPath = mask_to_path(mask, width, height, 0, 0);
+      cachedLocalPath = PathCmdList.parse(localPath);
+      cachedKey = key;
+    } catch (error) {
+      logSystemWarn('Failed to build pen outline.', { label: 'CanvasOverlaySVG', details: [error] });
+      cachedLocalPath = undefined;
+      cachedKey = undefined;
+    }
   });
 
   // Pen outline (logical coordinates)
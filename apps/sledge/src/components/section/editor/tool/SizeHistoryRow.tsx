@@ -5,6 +5,7 @@ import { color } from '@sledge/theme';
 import { showContextMenu } from '@sledge/ui';
 import { mask_to_path } from '@sledge/wasm';
 import { Component, createEffect, createMemo, createSignal, For, onMount, Show } from 'solid-js';
+import { logSystemWarn } from '~/features/log/service';
 import { EraserTool } from '~/features/tools/behaviors/draw/eraser/EraserTool';
 import { PenTool } from '~/features/tools/behaviors/draw/pen/PenTool';
 import { getCurrentPresetConfig, updateToolPresetConfig } from '~/features/tools/ToolController';
@@ -144,8 +145,13 @@ const PreviewSVG: Component<PreviewProps> = (props) => {
     if (shapeMask) {
       setShapeMask(shapeMask);
       const { mask, width, height, offsetX, offsetY } = shapeMask;
-      const localPath = mask_to_path(mask, width, height, offsetX + containerSize / 2, offsetY + containerSize / 2);
-      setPenOutlinePath(localPath);
+      try {
+        const localPath = mask_to_path(mask, width, height, offsetX + containerSize / 2, offsetY + containerSize / 2);
+        setPenOutlinePath(localPath);
+      } catch (error) {
+        logSystemWarn('Failed to build size preview.', { label: 'SizeHistoryRow', details: [error] });
+        setPenOutlinePath('');
+      }
     }
   };
 
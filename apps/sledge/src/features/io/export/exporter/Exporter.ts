@@ -1,6 +1,6 @@
 import { webGLRenderer } from '~/components/canvas/stacks/WebGLCanvas';
 import { convertToMimetype } from '~/features/io/FileExtensions';
-import { Layer } from '~/features/layer';
+import { flattenLayerList, Layer } from '~/features/layer';
 import { getAnvil } from '~/features/layer/anvil/AnvilManager';
 import { canvasStore } from '~/stores/ProjectStores';
 
@@ -10,9 +10,7 @@ export abstract class Exporter {
 }
 
 export async function convertCanvasToBlob(format: 'png' | 'jpeg' | 'webp_lossy', quality: number = 0.92, scale: number = 1): Promise<Blob> {
-  if (webGLRenderer === undefined) throw new Error('Export Error: Renderer not defined');
-
-  const buffer: Uint8ClampedArray<ArrayBuffer> = new Uint8ClampedArray(webGLRenderer.readPixelsFlipped());
+  const buffer = flattenLayerList();
   const offscreen = getScaledCanvas(buffer, scale);
   const mimeType = convertToMimetype(format);
 
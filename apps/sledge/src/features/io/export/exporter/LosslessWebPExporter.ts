@@ -1,13 +1,12 @@
 import { rawToWebp } from '@sledge/anvil';
 import { webGLRenderer } from '~/components/canvas/stacks/WebGLCanvas';
 import { Exporter, getScaledBuffer } from '~/features/io/export/exporter/Exporter';
-import { Layer } from '~/features/layer';
+import { flattenLayerList, Layer } from '~/features/layer';
 import { getAnvil } from '~/features/layer/anvil/AnvilManager';
 
 export class LosslessWebPExporter extends Exporter {
   async canvasToBlob(quality?: number, scale: number = 1): Promise<Blob> {
-    if (!webGLRenderer) throw new Error('Export Error: Renderer not defined');
-    const buffer: Uint8ClampedArray<ArrayBuffer> = new Uint8ClampedArray(webGLRenderer.readPixelsFlipped());
+    const buffer = flattenLayerList();
     const scaledBuffer = getScaledBuffer(buffer, scale);
     const webpBuffer = rawToWebp(scaledBuffer.data, scaledBuffer.width, scaledBuffer.height);
     const blob = new Blob([new Uint8ClampedArray(webpBuffer)], { type: 'image/webp' });
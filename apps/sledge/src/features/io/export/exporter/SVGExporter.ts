@@ -2,10 +2,11 @@ import { toUint8Array } from '@sledge/anvil';
 import { create_opacity_mask, mask_to_path } from '@sledge/wasm';
 import { webGLRenderer } from '~/components/canvas/stacks/WebGLCanvas';
 import { Exporter } from '~/features/io/export/exporter/Exporter';
-import { Layer } from '~/features/layer';
+import { flattenLayerList, Layer } from '~/features/layer';
 import { getAnvil } from '~/features/layer/anvil/AnvilManager';
 import { canvasStore } from '~/stores/ProjectStores';
 
+// wasm 側の入力検証エラー（サイズ不一致など）はそのまま throw し、exportImage が export failed として報告する
 export class SVGExporter extends Exporter {
   async canvasToBlob(quality?: number, scale: number = 1): Promise<Blob> {
     const { width, height } = canvasStore.canvas;
@@ -15,8 +16,7 @@ export class SVGExporter extends Exporter {
       throw new Error('SVG export is only supported for images 128x128 or smaller');
     }
 
-    if (webGLRenderer === undefined) throw new Error('Export Error: Renderer not defined');
-    const buffer = webGLRenderer.readPixelsFlipped();
+    const buffer = flattenLayerList();
 
     // wasmを使って不透明部分のマスクを作成
     const mask = create_opacity_mask(toUint8Array(buffer), width, height);
@@ -0,0 +1,41 @@
+import { hexWithSharpToRGBA, toUint8Array } from '@sledge/anvil';
+import { BaseLayerColorMode as WasmBaseLayerColorMode, LayerTree } from '@sledge/wasm';
+import { getAnvil } from '~/features/layer/anvil/AnvilManager';
+import { getBlendModeId } from '~/features/layer/model';
+import { BaseLayer } from '~/features/layer/types';
+import { canvasStore, layerListStore } from '~/stores/ProjectStores';
+
+const baseLayerColorModes = {
+  transparent: WasmBaseLayerColorMode.Transparent,
+  white: WasmBaseLayerColorMode.White,
+  black: WasmBaseLayerColorMode.Black,
+  custom: WasmBaseLayerColorMode.Custom,
+};
+
+function baseLayerCustomColor(baseLayer: BaseLayer): Uint8Array | undefined {
+  if (baseLayer.colorMode !== 'custom' || !baseLayer.customColor) return undefined;
+  return Uint8Array.from(hexWithSharpToRGBA(baseLayer.customColor));
+}
+
+/**
+ * レイヤーリスト全体（ベースレイヤー・ドット倍率込み）をWASMで1枚のRGBAに統合する
+ * WebGLの readPixels を使わないので上下反転は不要で、レイヤー数の上限もない
+ */
+export function flattenLayerList(): Uint8ClampedArray<ArrayBuffer> {
+  const { width, height } = canvasStore.canvas;
+  const { baseLayer, layers } = layerListStore;
+
+  const tree = new LayerTree(width, height, baseLayerColorModes[baseLayer.colorMode], baseLayerCustomColor(baseLayer), false);
+  try {
+    // layers は上から順なので下から積む
+    for (const layer of layers.toReversed()) {
+      if (!layer.enabled) continue;
+      const buffer = getAnvil(layer.id).getBufferPointer();
+      if (!buffer) continue;
+      tree.push_layer(toUint8Array(buffer), getBlendModeId(layer.mode), layer.opacity, true, false, Math.max(1, layer.dotMagnification), undefined);
+    }
+    return new Uint8ClampedArray(tree.flatten());
+  } finally {
+    tree.free();
+  }
+}
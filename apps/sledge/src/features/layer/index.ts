@@ -19,6 +19,9 @@ export {
   getBlendModeName,
 } from './model';
 
+// CPU flattening (WASM)
+export { flattenLayerList } from './flatten';
+
 // Service functions (stateful operations)
 export {
   activeIndex,
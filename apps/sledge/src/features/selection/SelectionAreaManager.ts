@@ -8,6 +8,7 @@ import {
   combine_masks_replace,
   combine_masks_subtract,
   fill_rect_mask,
+  MaskFormat,
   trim_mask_with_box,
 } from '@sledge/wasm';
 // import { getActiveAgent, getBufferOf } from '~/features/layer/agent/LayerAgentManager'; // legacy
@@ -267,10 +268,10 @@ class SelectionAreaManager {
       resultMask = new Uint8Array(combine_masks_replace(preview));
     } else if (interactStore.selectionEditMode === 'add') {
       // OR 合成: wasmで高速処理
-      resultMask = new Uint8Array(combine_masks_add(activeMask, preview));
+      resultMask = new Uint8Array(combine_masks_add(activeMask, preview, MaskFormat.Binary));
     } else if (interactStore.selectionEditMode === 'subtract') {
       // AND NOT: wasmで高速処理
-      resultMask = new Uint8Array(combine_masks_subtract(activeMask, preview));
+      resultMask = new Uint8Array(combine_masks_subtract(activeMask, preview, MaskFormat.Binary));
     } else {
       resultMask = activeMask;
     }
@@ -321,10 +322,10 @@ class SelectionAreaManager {
 
     // wasmで高速合成
     if (interactStore.selectionEditMode === 'subtract') {
-      return new Uint8Array(combine_masks_subtract(baseMask, previewMask));
+      return new Uint8Array(combine_masks_subtract(baseMask, previewMask, MaskFormat.Binary));
     } else {
       // add または replace の場合は OR 合成
-      return new Uint8Array(combine_masks_add(baseMask, previewMask));
+      return new Uint8Array(combine_masks_add(baseMask, previewMask, MaskFormat.Binary));
     }
   }
 
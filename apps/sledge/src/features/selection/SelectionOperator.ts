@@ -1,6 +1,6 @@
 import type { PackedDiffs, RawPixelData } from '@sledge/anvil';
 import { Vec2 } from '@sledge/core';
-import { combine_masks_subtract, trim_mask_with_box } from '@sledge/wasm';
+import { combine_masks_subtract, MaskFormat, trim_mask_with_box } from '@sledge/wasm';
 import { AnvilLayerHistoryAction, projectHistoryController } from '~/features/history';
 import { ConvertSelectionHistoryAction } from '~/features/history/actions/ConvertSelectionHistoryAction';
 import { createEntryFromRawBuffer, insertEntry, selectEntry } from '~/features/image_pool';
@@ -203,7 +203,7 @@ export function invertSelectionArea() {
   // 3) すべて 1 のマスクから現在のマスクを減算して反転を得る
   //    out = 1 & ~mask == ~mask
   let ones: Uint8Array | null = new Uint8Array(mask.length).fill(1);
-  const inverted = new Uint8Array(combine_masks_subtract(ones, mask));
+  const inverted = new Uint8Array(combine_masks_subtract(ones, mask, MaskFormat.Binary));
 
   ones = null;
   selection.setMask(inverted);
@@ -229,7 +229,7 @@ export const computeMaskBBox = (
   for (let y = 0; y < height; y++) {
     const row = y * width;
     for (let x = 0; x < width; x++) {
-      if (mask[row + x] === 1) {
+      if (mask[row + x] !== 0) {
         if (x < minX) minX = x;
         if (y < minY) minY = y;
         if (x > maxX) maxX = x;
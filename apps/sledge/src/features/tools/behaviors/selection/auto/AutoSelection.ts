@@ -1,6 +1,7 @@
 import { Anvil, toUint8Array } from '@sledge/anvil';
 import { auto_select_region_mask } from '@sledge/wasm';
 import { getAnvil } from '~/features/layer/anvil/AnvilManager';
+import { logSystemWarn } from '~/features/log/service';
 import { selectionManager } from '~/features/selection/SelectionAreaManager';
 import { SelectionBase } from '~/features/tools/behaviors/selection/SelectionBase';
 import { ToolArgs } from '~/features/tools/behaviors/ToolBehavior';
@@ -54,7 +55,12 @@ export class AutoSelection extends SelectionBase {
     if (!buffer) return undefined;
     const src = toUint8Array(buffer); // RGBA buffer
     // connectivity は現状 4 固定（0を指定し内部で4接続扱い）
-    const mask = auto_select_region_mask(src, width, height, position.x, position.y, threshold ?? 0, 4);
-    return mask;
+    try {
+      return auto_select_region_mask(src, width, height, position.x, position.y, threshold ?? 0, 4);
+    } catch (error) {
+      // バッファとサイズが合わない場合は選択しない
+      logSystemWarn('Failed to compute auto selection.', { label: 'AutoSelection', details: [error] });
+      return undefined;
+    }
   }
 }
@@ -1,4 +1,4 @@
-import { fill_lasso_selection } from '@sledge/wasm';
+import { fill_lasso_selection, FillRule } from '@sledge/wasm';
 import { getCurrentWebview } from '@tauri-apps/api/webview';
 import { getAnvil } from '~/features/layer/anvil/AnvilManager';
 import { logSystemWarn } from '~/features/log/service';
@@ -114,7 +114,8 @@ export class LassoSelection extends SelectionBase {
 
     // WASM関数を使用してマスクを更新
     try {
-      fill_lasso_selection(this.previewFragment.partialMask, clampedWidth, clampedHeight, new Float32Array(localPoints), mode);
+      const fillRule = mode === 'evenodd' ? FillRule.EvenOdd : FillRule.NonZero;
+      fill_lasso_selection(this.previewFragment.partialMask, clampedWidth, clampedHeight, new Float32Array(localPoints), fillRule);
     } catch (error) {
       logSystemWarn('Lasso selection WASM call failed.', { label: 'LassoSelection', details: [error] });
     }
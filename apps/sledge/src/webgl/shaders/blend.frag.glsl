@@ -147,22 +147,23 @@ void main() {
   for (int i = startLayer; i < u_layerCount; ++i) {
     vec4 src = texture(u_texArray, vec3(v_uv, float(i))) * u_opacities[i];
     int mode = u_blendModes[i];
+    // mode は blendModeIds（features/layer/model.ts）の値。13 は Rust 側の LayerBlendMode::LinearDodge
     if (mode == 1) {
       dst = blendMultiply(src, dst);
     } else if (mode == 2) {
-      dst = blendAdd(src, dst);
-    } else if (mode == 3) {
       dst = blendScreen(src, dst);
-    } else if (mode == 4) {
+    } else if (mode == 3) {
       dst = blendOverlay(src, dst);
-    } else if (mode == 5) {
+    } else if (mode == 4) {
       dst = blendSoftLight(src, dst);
-    } else if (mode == 6) {
+    } else if (mode == 5) {
       dst = blendHardLight(src, dst);
-    } else if (mode == 7) {
+    } else if (mode == 6) {
       dst = blendLinearLight(src, dst);
-    } else if (mode == 8) {
+    } else if (mode == 7) {
       dst = blendVividLight(src, dst);
+    } else if (mode == 13) {
+      dst = blendAdd(src, dst);
     } else {
       dst = blendNormal(src, dst);
     }
@@ -1,62 +1,133 @@
 /* tslint:disable */
 /* eslint-disable */
+/**
+ * 選択範囲マスクから頂点配列 + ループ境界 + 種別の形でアウトラインを生成
+ * SVG文字列を経由せずに WebGL / Canvas2D で直接描画するためのもの
+ */
+export function mask_to_polylines(mask: Uint8Array, width: number, height: number, offset_x: number, offset_y: number): OutlinePolylines;
+/**
+ * 表示範囲と交わる部分のアウトラインだけをスクリーン座標のSVGパスとして生成
+ * view_x, view_y, view_width, view_height: 表示範囲（キャンバス座標）
+ * zoom: キャンバス1ピクセルあたりのスクリーンピクセル数
+ * 出力座標は ((キャンバス座標 - view) * zoom)。
+ * 表示範囲の外へはみ出すループは閉じず、範囲内の部分だけを開いた折れ線（Zなし）として出力する
+ */
+export function mask_to_path_in_viewport(mask: Uint8Array, width: number, height: number, view_x: number, view_y: number, view_width: number, view_height: number, zoom: number): string;
+/**
+ * ソフトマスク（0-255 の被覆率）の等値線をSVGパス文字列として生成
+ * 値はそのまま被覆率として読むので、0/1 の2値マスクには mask_to_path を使う
+ * threshold: この値以上を選択範囲内とみなす（省略時は 128 = 50%、0 は 1 として扱う）
+ */
+export function soft_mask_to_path(mask: Uint8Array, width: number, height: number, threshold: number | null | undefined, offset_x: number, offset_y: number): string;
+/**
+ * 選択範囲マスクから直線と3次ベジェ曲線で近似したSVGパス文字列を生成（potrace方式）
+ * alpha_max: 角とみなすしきい値（potraceの alphamax。既定は 1.0、0 で全て角、4/3 以上で角なし）
+ * opt_tolerance: 連続する曲線をまとめるときの許容誤差（ピクセル単位。既定は 0.2、0 以下でまとめない）
+ */
+export function mask_to_smooth_path(mask: Uint8Array, width: number, height: number, offset_x: number, offset_y: number, alpha_max: number, opt_tolerance: number): string;
+/**
+ * ソフトマスク（0-255）の等値線を mask_to_polylines と同じ形式で生成
+ */
+export function soft_mask_to_polylines(mask: Uint8Array, width: number, height: number, threshold: number | null | undefined, offset_x: number, offset_y: number): OutlinePolylines;
 /**
  * 選択範囲マスクからSVGパス文字列を生成
  */
 export function mask_to_path(mask: Uint8Array, width: number, height: number, offset_x: number, offset_y: number): string;
 /**
- * 選択範囲制限モードに応じてピクセルバッファをフィルタリングする
- * original_buffer: 元のピクセルバッファ (RGBA)
- * selection_mask: 選択範囲のマスク (0 or 1)
- * mode: 制限モード ("inside", "outside", "none")
- * width, height: 画像のサイズ
+ * 画像から最大 color_count 色のパレットを抽出する
+ * method: 減色の方法
+ * sort: パレットの並び順
  *
- * "inside": 選択範囲外を透明化
- * "outside": 選択範囲内を透明化
- * "none": 元のバッファをそのまま返す
+ * 戻り値は [r, g, b, r, g, b, ...]（dither_to_palette などにそのまま渡せる）。
+ * 完全に透明なピクセルは数えず、画像に含まれる色が color_count より少なければその色数になる。
+ * 結果は入力だけで決まる（乱数は使わない）
  */
-export function filter_by_selection_mask(original_buffer: Uint8Array, selection_mask: Uint8Array, mode: string, width: number, height: number): Uint8Array;
+export function extract_palette(buffer: Uint8Array, width: number, height: number, color_count: number, method: QuantizeMethod, sort: PaletteSort): Uint8Array;
 /**
- * 2つのバッファを合成する（FloodFill結果を元のバッファに適用）
- * base_buffer: ベースとなるピクセルバッファ (RGBA)
- * overlay_buffer: 重ねるピクセルバッファ (RGBA) - FloodFillの結果
- * selection_mask: 選択範囲のマスク (0 or 1)
- * mode: 制限モード ("inside", "outside", "none")
- * width, height: 画像のサイズ
+ * 画像からパレットを抽出し、画像をそのパレットの色に置き換える（その場で書き換える）
+ * dither: 置き換えるときのディザリング（None なら最も近い色にするだけ）
+ *
+ * 戻り値は抽出したパレット（extract_palette と同じ形式）。
+ * アルファはそのまま残し、完全に透明なピクセルは変えない
  */
-export function composite_fill_result(base_buffer: Uint8Array, overlay_buffer: Uint8Array, selection_mask: Uint8Array, mode: string, width: number, height: number): Uint8Array;
-export function trim_mask_with_box(mask: Uint8Array, mask_width: number, mask_height: number, box_x: number, box_y: number, box_width: number, box_height: number): Uint8Array;
+export function quantize_image(buffer: Uint8Array, width: number, height: number, color_count: number, method: QuantizeMethod, sort: PaletteSort, dither: DitherMethod): Uint8Array;
+/**
+ * レイヤーを下から順に合成する（WebGLの LayerMergeRenderer と同じ結果になるCPU版）
+ * layers: 同じサイズのRGBAバッファを下から順に layer_count 枚連結したもの
+ * modes: レイヤーごとのブレンドモード（LayerBlendMode の値）
+ * opacities: レイヤーごとの不透明度 (0.0-1.0)
+ * enabled: レイヤーごとの表示フラグ（0 なら無視する）
+ * clip_to_below: レイヤーごとのクリッピングフラグ（0 以外なら下のレイヤーでクリッピング）
+ * has_mask: レイヤーごとのマスク有無（0 以外ならマスクあり）
+ * masks: マスクありのレイヤーのマスク（width * height、0-255 の被覆率）を下から順に連結したもの
+ *
+ * マスクとクリッピングはアルファだけに掛かる（不透明度と違い RGB は変えない）。
+ * ベースレイヤーなしで合成し、最下層の有効なレイヤーがそのまま出発点になる。
+ * 各モードはシェーダーの同名の関数と同じ f32 の式で計算するので、GPUとの差は丸めによる各チャンネル ±1 以内。
+ * modes の値は blendModeIds と同じで、シェーダーの u_blendModes と同じ値を渡せばよい
+ */
+export function composite_layers(layers: Uint8Array, width: number, height: number, layer_count: number, modes: Uint8Array, opacities: Float32Array, enabled: Uint8Array, clip_to_below: Uint8Array, has_mask: Uint8Array, masks: Uint8Array): Uint8Array;
+export function create_opacity_mask(buffer: Uint8Array, width: number, height: number): Uint8Array;
 /**
  * ピクセルデータを上下反転する関数
  * WebGLのreadPixelsは下から上の順序で返すため、通常の画像として使う場合は反転が必要
  */
 export function flip_pixels_vertically(pixels: Uint8Array, width: number, height: number): void;
 /**
- * マスク合成：OR演算 (add mode)
+ * 選択範囲マスクを軸平行な矩形の集合に分解（貪欲法）
+ * 戻り値は [x, y, width, height, x, y, width, height, ...]
+ * 矩形に近い選択範囲なら少数の RectFragment として履歴に残したり、
+ * fill_rects / clear_rects で行単位の操作にしたりできる
  */
-export function combine_masks_add(base_mask: Uint8Array, preview_mask: Uint8Array): Uint8Array;
+export function mask_to_rects(mask: Uint8Array, width: number, height: number): Uint32Array;
 /**
- * マスク合成：AND NOT演算 (subtract mode)
+ * 矩形の列の内側を指定色で塗りつぶす（合成せずに置き換える）
+ * rects: mask_to_rects 形式の [x, y, width, height, ...]。画像からはみ出した部分は無視する
  */
-export function combine_masks_subtract(base_mask: Uint8Array, preview_mask: Uint8Array): Uint8Array;
+export function fill_rects(buffer: Uint8Array, width: number, height: number, rects: Uint32Array, r: number, g: number, b: number, a: number): void;
 /**
- * マスク合成：置換 (replace mode)
+ * 矩形の列の内側を透明にする
+ * rects: mask_to_rects 形式の [x, y, width, height, ...]。画像からはみ出した部分は無視する
  */
-export function combine_masks_replace(preview_mask: Uint8Array): Uint8Array;
+export function clear_rects(buffer: Uint8Array, width: number, height: number, rects: Uint32Array): void;
 /**
- * 矩形をマスクに描画
+ * レイヤーの不透明部分を選択範囲マスクにする（レイヤーのCtrl+クリック相当）
+ * layers: 同じサイズのRGBAバッファを layer_count 枚連結したもの
+ * alpha_threshold: この値より大きいアルファを内容とみなす（0 なら透明以外すべて）
+ * format: 戻り値のマスクの形式（Coverage ならアルファをそのまま被覆率にする）
+ * mode: 複数レイヤーの和・積（アルファの最大・最小）
+ *
+ * 戻り値は同じ format で combine_masks_* にそのまま渡せるマスク。
+ * Coverage のときもしきい値以下のピクセルは 0 になる
  */
-export function fill_rect_mask(mask: Uint8Array, width: number, height: number, start_x: number, start_y: number, rect_width: number, rect_height: number): void;
-/**
- * マスクオフセット適用（commitOffset用）
- */
-export function apply_mask_offset(mask: Uint8Array, width: number, height: number, offset_x: number, offset_y: number): Uint8Array;
-export function create_opacity_mask(buffer: Uint8Array, width: number, height: number): Uint8Array;
+export function select_layer_opacity(layers: Uint8Array, width: number, height: number, layer_count: number, alpha_threshold: number, format: MaskFormat, mode: LayerCombineMode): Uint8Array;
 /**
  * しきい値付きの自動選択（領域抽出）
  * 入力バッファは RGBA 連続の &[u8]。変更せず、選択マスク(幅*高さ, 0/1)を返す。
  */
 export function auto_select_region_mask(buffer: Uint8Array, width: number, height: number, start_x: number, start_y: number, threshold: number, _connectivity: number): Uint8Array;
+/**
+ * 画像を任意のパレットの色だけで表す（その場で書き換える）
+ * palette: [r, g, b, r, g, b, ...]（プロジェクトのパレットやスウォッチなど）
+ * method: ディザリングの方法
+ * strength: 誤差拡散では拡散する誤差、組織的ディザリングでは閾値の振れ幅に掛ける係数 (0.0-1.0)
+ * serpentine: true なら誤差拡散で行ごとに走査方向を反転する（模様の偏りが減る）
+ *
+ * アルファはそのまま残し、完全に透明なピクセルは変えない（誤差も受け渡さない）
+ */
+export function dither_to_palette(buffer: Uint8Array, width: number, height: number, palette: Uint8Array, method: DitherMethod, strength: number, serpentine: boolean): void;
+/**
+ * 選択範囲の境界線をレイヤーのピクセルバッファに描画する（選択範囲の境界線を描く）
+ * buffer: 描画先のピクセルバッファ (RGBA)
+ * mask: 選択範囲のマスク（0 以外を選択とみなす。MaskFormat::Binary と同じ）
+ * stroke_width: 線幅（ピクセル）
+ * position: 線の位置
+ * r, g, b, a: 線の色（source-overで重ねる）
+ *
+ * 境界からの距離はピクセル中心間のユークリッド距離で測り、距離が線幅以下のピクセルを塗る。
+ * 線幅1では上下左右の隣接だけが距離1になるので、角が二重にならない1ピクセルの線になる。
+ */
+export function stroke_selection(buffer: Uint8Array, mask: Uint8Array, width: number, height: number, stroke_width: number, position: StrokePosition, r: number, g: number, b: number, a: number): void;
 /**
  * Lasso選択のためのスキャンライン塗りつぶし実装
  *
@@ -67,12 +138,421 @@ export function auto_select_region_mask(buffer: Uint8Array, width: number, heigh
  * - メモリ効率的な実装
  * - evenodd/nonzero塗りつぶし規則の選択
  */
-export function fill_lasso_selection(mask: Uint8Array, width: number, height: number, points: Float32Array, fill_rule: string): boolean;
+export function fill_lasso_selection(mask: Uint8Array, width: number, height: number, points: Float32Array, fill_rule: FillRule): boolean;
 /**
  * 選択範囲制限付きLasso選択
  */
-export function fill_lasso_selection_with_mask(mask: Uint8Array, width: number, height: number, points: Float32Array, existing_mask: Uint8Array, limit_mode: string): boolean;
+export function fill_lasso_selection_with_mask(mask: Uint8Array, width: number, height: number, points: Float32Array, existing_mask: Uint8Array, limit_mode: SelectionLimitMode): boolean;
 /**
  * Point-in-polygon アルゴリズムを使用した直接的な実装（小さなポリゴン用）
  */
 export function fill_lasso_selection_point_in_polygon(mask: Uint8Array, width: number, height: number, points: Float32Array): boolean;
+/**
+ * 複数レイヤーをまとめた内容の範囲を求める
+ * layers: 同じサイズのRGBAバッファを layer_count 枚連結したもの
+ * 戻り値は content_bounds と同じ
+ */
+export function content_bounds_of_layers(layers: Uint8Array, width: number, height: number, layer_count: number, alpha_threshold: number): Uint32Array;
+/**
+ * アルファがしきい値を超えるピクセルを囲む最小の矩形を求める（トリミング用）
+ * alpha_threshold: この値より大きいアルファを内容とみなす（0 なら create_opacity_mask と同じ判定）
+ * 戻り値は [x, y, width, height]。内容がなければ空配列
+ */
+export function content_bounds(buffer: Uint8Array, width: number, height: number, alpha_threshold: number): Uint32Array;
+/**
+ * RGBAピクセルバッファを色ごとに矩形へ分解したSVGドキュメントを生成（ピクセルアート向け）
+ * 座標はすべて整数で、常に shape-rendering="crispEdges" を付けるので矩形の継ぎ目に隙間が出ない
+ * scale: 整数倍率（座標そのものに掛けるので viewBox も拡大後のサイズになる）
+ * as_path: true なら色ごとに1つの <path> にまとめ、false なら <g> 内に <rect> を並べる
+ */
+export function export_svg_rects(buffer: Uint8Array, width: number, height: number, scale: number, as_path: boolean): string;
+/**
+ * RGBAピクセルバッファを色ごとに輪郭抽出したSVGドキュメントを生成
+ * 完全に同じRGBAを持つピクセルを1色としてまとめ、色ごとに <path> を1つ出力する
+ * scale: width/height 属性に掛ける倍率（viewBox は元のサイズのまま）
+ * crisp_edges: true なら shape-rendering="crispEdges" を付ける
+ */
+export function export_svg_traced(buffer: Uint8Array, width: number, height: number, scale: number, crisp_edges: boolean): string;
+/**
+ * ガウスぼかし（その場で書き換える）
+ * radius: カーネルの半径（ピクセル）。0 なら sigma から決める
+ * sigma: 標準偏差。0 以下なら radius / 3（ボックスで近似するときは sigma だけで決まる）
+ * edge: 画像の外側の扱い
+ * selection_mask: 指定すると被覆率に応じてぼかし結果と元の画像を混ぜる
+ * mask_format: selection_mask の形式
+ * linear_light: true なら linear light でぼかす
+ *
+ * 乗算済みアルファで計算するので、透明部分の色（多くは黒）が縁ににじまない。
+ * 半径が大きいときは累積和によるボックスぼかし3回で近似するので、半径に関係なくピクセル数に比例した時間で終わる
+ * （3840x2160 でネイティブ実行 0.5 秒ほど、linear_light なら 1 秒ほど。ドラッグ中のプレビューには縮小画像を使うこと）。
+ * 作業用に横方向の結果を 16bit の RGBA で持つので、buffer の 2 倍（3840x2160 で約 66MB）のメモリを確保する
+ */
+export function gaussian_blur(buffer: Uint8Array, width: number, height: number, radius: number, sigma: number, edge: BlurEdgeMode, selection_mask: Uint8Array | null | undefined, mask_format: MaskFormat, linear_light: boolean): void;
+/**
+ * ボックスぼかし（その場で書き換える）
+ * radius: 半径（(2 * radius + 1) 四方の平均）
+ * iterations: 繰り返し回数（3 回でガウスぼかしに近くなる、0 なら何もしない）
+ * edge, selection_mask, mask_format, linear_light: gaussian_blur と同じ
+ */
+export function box_blur(buffer: Uint8Array, width: number, height: number, radius: number, iterations: number, edge: BlurEdgeMode, selection_mask: Uint8Array | null | undefined, mask_format: MaskFormat, linear_light: boolean): void;
+/**
+ * RGBAバッファに別のRGBAバッファを貼り付ける
+ * dst: 貼り付け先 (RGBA, width x height)
+ * src: 貼り付けるバッファ (RGBA, src_width x src_height)
+ * x, y: 貼り付け位置（負の値やキャンバス外へのはみ出しは切り取られる）
+ * blend: 合成方法
+ * mask: 省略可。src と同じサイズのマスク。被覆率に応じて src を適用する
+ * mask_format: mask の形式
+ */
+export function paste_region(dst: Uint8Array, width: number, height: number, src: Uint8Array, src_width: number, src_height: number, x: number, y: number, blend: PasteBlendMode, mask: Uint8Array | null | undefined, mask_format: MaskFormat): void;
+/**
+ * RGBAバッファから矩形領域を切り出す
+ * buffer: 元のピクセルバッファ (RGBA, width x height)
+ * rect_x, rect_y, rect_width, rect_height: 切り出す矩形（キャンバス外にはみ出してもよい）
+ * mask: 省略可。キャンバスと同じサイズの選択範囲マスク。選択範囲外のピクセルは透明になり、
+ *       被覆率が中間の値ならアルファに被覆率を掛ける
+ * mask_format: mask の形式
+ *
+ * 戻り値は rect_width x rect_height のRGBA。キャンバス外の部分は透明
+ */
+export function extract_region(buffer: Uint8Array, width: number, height: number, rect_x: number, rect_y: number, rect_width: number, rect_height: number, mask: Uint8Array | null | undefined, mask_format: MaskFormat): Uint8Array;
+/**
+ * 選択範囲制限モードに応じてピクセルバッファをフィルタリングする
+ * original_buffer: 元のピクセルバッファ (RGBA)
+ * selection_mask: 選択範囲のマスク
+ * mask_format: selection_mask の形式
+ * mode: 制限モード
+ * width, height: 画像のサイズ
+ *
+ * Inside: 選択範囲外を透明化（アルファに被覆率を掛ける）
+ * Outside: 選択範囲内を透明化（アルファに 1 - 被覆率 を掛ける）
+ * None: 元のバッファをそのまま返す
+ */
+export function filter_by_selection_mask(original_buffer: Uint8Array, selection_mask: Uint8Array, mask_format: MaskFormat, mode: SelectionLimitMode, width: number, height: number): Uint8Array;
+export function trim_mask_with_box(mask: Uint8Array, mask_width: number, mask_height: number, box_x: number, box_y: number, box_width: number, box_height: number): Uint8Array;
+/**
+ * 描画・エフェクト後のバッファに描画前のアルファを戻す（透明部分の保護）
+ * buffer: 描画後のピクセルバッファ (RGBA)。その場で書き換える
+ * original_buffer: 描画前のピクセルバッファ (RGBA)
+ * width, height: 画像のサイズ
+ *
+ * 色は描画後のものを使い、アルファだけを描画前に戻す。描画前に透明だったピクセルは透明のまま、
+ * 描画後に透明になったピクセルは描画前の色に戻す。
+ * 不透明なピクセルでは source-atop で描いたのと同じ結果になる（半透明部分は近似）
+ */
+export function preserve_alpha(buffer: Uint8Array, original_buffer: Uint8Array, width: number, height: number): void;
+/**
+ * 2つのバッファを合成する（FloodFill結果を元のバッファに適用）
+ * base_buffer: ベースとなるピクセルバッファ (RGBA)
+ * overlay_buffer: 重ねるピクセルバッファ (RGBA) - FloodFillの結果
+ * selection_mask: 選択範囲のマスク
+ * mask_format: selection_mask の形式
+ * mode: 制限モード
+ * width, height: 画像のサイズ
+ * alpha_lock: true なら透明部分を保護する（source-atop で重ね、ベースのアルファを変えない）
+ *
+ * overlay は source-over でベースに重ね、そのアルファに被覆率を掛ける
+ * (Outside では 1 - 被覆率、None では常に 1)。
+ * ベースと同じピクセル（FloodFillで変化していない所）は重ねない。
+ * None で alpha_lock も false なら overlay をそのまま返す（selection_mask は見ない）
+ */
+export function composite_fill_result(base_buffer: Uint8Array, overlay_buffer: Uint8Array, selection_mask: Uint8Array, mask_format: MaskFormat, mode: SelectionLimitMode, width: number, height: number, alpha_lock: boolean): Uint8Array;
+/**
+ * マスク合成：OR演算 (add mode)
+ * format: 2つのマスクと戻り値の形式。被覆率のマスクでは大きい方の被覆率を取る
+ */
+export function combine_masks_add(base_mask: Uint8Array, preview_mask: Uint8Array, format: MaskFormat): Uint8Array;
+/**
+ * 矩形をマスクに描画
+ */
+export function fill_rect_mask(mask: Uint8Array, width: number, height: number, start_x: number, start_y: number, rect_width: number, rect_height: number): void;
+/**
+ * 矩形の列からマスクを作る（mask_to_rects の逆）
+ * rects: [x, y, width, height, ...]。画像からはみ出した部分は切り捨てる
+ */
+export function rects_to_mask(rects: Uint32Array, width: number, height: number): Uint8Array;
+/**
+ * マスクオフセット適用（commitOffset用）
+ * 値はそのまま移すので、2値マスクにも被覆率のマスクにも使える
+ */
+export function apply_mask_offset(mask: Uint8Array, width: number, height: number, offset_x: number, offset_y: number): Uint8Array;
+/**
+ * マスク合成：AND NOT演算 (subtract mode)
+ * format: 2つのマスクと戻り値の形式。被覆率のマスクでは base の被覆率に (1 - preview の被覆率) を掛ける
+ */
+export function combine_masks_subtract(base_mask: Uint8Array, preview_mask: Uint8Array, format: MaskFormat): Uint8Array;
+/**
+ * マスク合成：置換 (replace mode)
+ */
+export function combine_masks_replace(preview_mask: Uint8Array): Uint8Array;
+/**
+ * ベースレイヤーの色モード（アプリ側の BaseLayerColorMode と同じ）
+ */
+export enum BaseLayerColorMode {
+  Transparent = 0,
+  White = 1,
+  Black = 2,
+  Custom = 3,
+}
+/**
+ * 画像の外側をどう扱うか
+ */
+export enum BlurEdgeMode {
+  /**
+   * 端のピクセルを延長する
+   */
+  Clamp = 0,
+  /**
+   * 反対側の端から折り返す（タイル用）
+   */
+  Wrap = 1,
+  /**
+   * 外側は透明とみなす
+   */
+  Transparent = 2,
+}
+/**
+ * ディザリングの方法
+ */
+export enum DitherMethod {
+  /**
+   * ディザリングなし（最も近い色に置き換えるだけ）
+   */
+  None = 0,
+  FloydSteinberg = 1,
+  Atkinson = 2,
+  JarvisJudiceNinke = 3,
+  Sierra = 4,
+  /**
+   * 2x2 の組織的ディザリング
+   */
+  Bayer2 = 5,
+  /**
+   * 4x4 の組織的ディザリング
+   */
+  Bayer4 = 6,
+  /**
+   * 8x8 の組織的ディザリング
+   */
+  Bayer8 = 7,
+}
+/**
+ * ポリゴンの塗りつぶし規則
+ */
+export enum FillRule {
+  EvenOdd = 0,
+  NonZero = 1,
+}
+/**
+ * レイヤーのブレンドモード
+ * 0-7 はアプリ側の blendModeIds（features/layer/model.ts）と同じで、blend.frag.glsl もこの値で分岐する
+ * 8 以降は W3C Compositing and Blending の式による拡張モード（シェーダーは 13 の LinearDodge を blendAdd で扱う）
+ */
+export enum LayerBlendMode {
+  Normal = 0,
+  Multiply = 1,
+  Screen = 2,
+  Overlay = 3,
+  SoftLight = 4,
+  HardLight = 5,
+  LinearLight = 6,
+  VividLight = 7,
+  Darken = 8,
+  Lighten = 9,
+  ColorDodge = 10,
+  ColorBurn = 11,
+  LinearBurn = 12,
+  LinearDodge = 13,
+  Difference = 14,
+  Exclusion = 15,
+  Subtract = 16,
+  Divide = 17,
+  Hue = 18,
+  Saturation = 19,
+  Color = 20,
+  Luminosity = 21,
+}
+/**
+ * 複数レイヤーの結果のまとめ方
+ */
+export enum LayerCombineMode {
+  /**
+   * いずれかのレイヤーに内容があれば選択（和）
+   */
+  Union = 0,
+  /**
+   * すべてのレイヤーに内容がある所だけ選択（積）
+   */
+  Intersection = 1,
+}
+/**
+ * アウトラインのループ種別
+ */
+export enum LoopKind {
+  /**
+   * 外周（選択範囲の外側の境界）
+   */
+  Outer = 0,
+  /**
+   * 穴（選択範囲内の非選択領域の境界）
+   */
+  Hole = 1,
+}
+/**
+ * 選択マスクの値の形式
+ *
+ * 2値マスクと被覆率のマスクは同じ 0-255 の範囲の値を使うので、値からは区別できない。
+ * マスクを受け取る関数は形式を引数で受け取り、それに従って読む
+ */
+export enum MaskFormat {
+  /**
+   * 0/1 の2値（0 以外はすべて完全に選択とみなす）
+   */
+  Binary = 0,
+  /**
+   * 0-255 の被覆率（255 で完全に選択）
+   */
+  Coverage = 1,
+}
+/**
+ * 抽出したパレットの並び順
+ */
+export enum PaletteSort {
+  /**
+   * 使われているピクセル数の多い順
+   */
+  Frequency = 0,
+  /**
+   * 無彩色（明るさ順）のあとに色相順
+   */
+  Hue = 1,
+}
+/**
+ * 貼り付け時の合成方法
+ */
+export enum PasteBlendMode {
+  /**
+   * 貼り付け先のピクセルを置き換える
+   */
+  Replace = 0,
+  /**
+   * 貼り付け先に source-over で重ねる
+   */
+  SourceOver = 1,
+}
+/**
+ * 減色（パレット抽出）の方法
+ */
+export enum QuantizeMethod {
+  /**
+   * 色の分布を最も広いチャンネルの中央値で分割していく
+   */
+  MedianCut = 0,
+  /**
+   * OKLab 空間での k-means（知覚的に近い色をまとめる。最も遅い）
+   */
+  KMeansOklab = 1,
+  /**
+   * 8 分木で色を集め、使用頻度の低い枝から統合する（最も速い）
+   */
+  Octree = 2,
+}
+/**
+ * 選択範囲制限モード
+ */
+export enum SelectionLimitMode {
+  /**
+   * 制限なし
+   */
+  None = 0,
+  /**
+   * 選択範囲内のみ
+   */
+  Inside = 1,
+  /**
+   * 選択範囲外のみ
+   */
+  Outside = 2,
+}
+/**
+ * 境界線を描く位置
+ */
+export enum StrokePosition {
+  /**
+   * 選択範囲の内側（キャンバスの端も境界とみなす）
+   */
+  Inside = 0,
+  /**
+   * 境界をまたぐ（奇数幅の余りは内側）
+   */
+  Center = 1,
+  /**
+   * 選択範囲の外側
+   */
+  Outside = 2,
+}
+/**
+ * レイヤーツリー全体を1枚の画像に統合する（エクスポート・サムネイル用）
+ *
+ * 下から順に push_layer でレイヤーを積み、begin_group / end_group でグループを入れ子にする。
+ * 組み立て終わったら flatten で1枚の画像に統合する。
+ * WebGLRenderer の描画（ベースレイヤーあり）と同じ結果を readPixels なしで得られる。
+ * 出力は上下反転していないので flip_pixels_vertically は不要
+ *
+ * レイヤーは push_layer の時点で合成し、バッファは保持しない。
+ * 保持するのは合成途中の結果（f32 の RGBA とクリッピングの土台のアルファで 1ピクセル 20 バイト）だけで、
+ * 開いているグループの入れ子1段ごとに1枚ずつ増える。メモリはレイヤー数ではなく入れ子の深さに比例する
+ * （4096x4096 なら1段あたり約 335MB）
+ */
+export class LayerTree {
+  free(): void;
+  [Symbol.dispose](): void;
+  /**
+   * 現在のグループ（グループ外ならルート）の一番上にレイヤーを重ねる
+   * buffer: キャンバスと同じサイズのRGBAバッファ（呼び出し後は保持しない）
+   * mode, opacity (0.0-1.0), enabled, clip_to_below: composite_layers のレイヤーごとの値と同じ
+   * dot_magnification: ドット倍率（1 以上）。マスクには掛からない
+   * mask: レイヤーマスク（キャンバスと同じサイズ、0-255 の被覆率）
+   */
+  push_layer(buffer: Uint8Array, mode: LayerBlendMode, opacity: number, enabled: boolean, clip_to_below: boolean, dot_magnification: number, mask?: Uint8Array | null): void;
+  /**
+   * グループを開始する。end_group までに積んだレイヤーがこのグループの子になる
+   * isolated: true なら子を透明の上で先に合成してから mode と opacity で重ねる。
+   *           false（パススルー）なら子を下のレイヤーに直接重ね、mode は使わず opacity で元の結果と補間する
+   */
+  begin_group(mode: LayerBlendMode, opacity: number, enabled: boolean, isolated: boolean): void;
+  /**
+   * width, height: キャンバスのサイズ
+   * base_mode: ベースレイヤーの色モード
+   * base_color: Custom のときの色 [r, g, b, a]。省略時は透明
+   * linear_light: true なら色を linear light に変換し、RGB に不透明度を掛けない source-over で合成する
+   *               （境界が暗く濁らない）。false ならシェーダーと同じく sRGB の値のまま合成する
+   */
+  constructor(width: number, height: number, base_mode: BaseLayerColorMode, base_color: Uint8Array | null | undefined, linear_light: boolean);
+  /**
+   * ここまでに積んだレイヤーを1枚のRGBA画像に統合する
+   */
+  flatten(): Uint8Array;
+  /**
+   * 最も内側のグループを閉じ、その結果を親に重ねる
+   *
+   * 分離グループは子の合成結果を1枚のレイヤーとして扱い、後続のクリッピングの土台にもなる。
+   * パススルーグループは土台にならないので、直後のクリッピングするレイヤーは通常のレイヤーとして描く
+   */
+  end_group(): void;
+}
+/**
+ * 構造化されたアウトライン
+ * vertices: 全ループの頂点を連結した [x0, y0, x1, y1, ...]
+ * offsets: 各ループの開始位置（頂点単位）。末尾に総頂点数が入るので長さはループ数 + 1
+ * kinds: 各ループの種別 (LoopKind)
+ */
+export class OutlinePolylines {
+  private constructor();
+  free(): void;
+  [Symbol.dispose](): void;
+  readonly loop_count: number;
+  readonly kinds: Uint8Array;
+  readonly offsets: Uint32Array;
+  readonly vertices: Float32Array;
+}
@@ -1,23 +1,58 @@
 /* tslint:disable */
 /* eslint-disable */
 export const memory: WebAssembly.Memory;
-export const mask_to_path: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number];
-export const filter_by_selection_mask: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number];
-export const composite_fill_result: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number, j: number) => [number, number];
-export const trim_mask_with_box: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number];
-export const flip_pixels_vertically: (a: number, b: number, c: any, d: number, e: number) => void;
-export const combine_masks_add: (a: number, b: number, c: number, d: number) => [number, number];
-export const combine_masks_subtract: (a: number, b: number, c: number, d: number) => [number, number];
+export const __wbg_outlinepolylines_free: (a: number, b: number) => void;
+export const mask_to_path: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number, number];
+export const mask_to_path_in_viewport: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number) => [number, number, number, number];
+export const mask_to_polylines: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number];
+export const mask_to_smooth_path: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number, number, number];
+export const outlinepolylines_kinds: (a: number) => [number, number];
+export const outlinepolylines_loop_count: (a: number) => number;
+export const outlinepolylines_offsets: (a: number) => [number, number];
+export const outlinepolylines_vertices: (a: number) => [number, number];
+export const soft_mask_to_path: (a: number, b: number, c: number, d: number, e: number, f: number, g: number) => [number, number, number, number];
+export const soft_mask_to_polylines: (a: number, b: number, c: number, d: number, e: number, f: number, g: number) => [number, number, number];
+export const extract_palette: (a: number, b: number, c: number, d: number, e: number, f: number, g: number) => [number, number, number, number];
+export const quantize_image: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number) => [number, number, number, number];
+export const clear_rects: (a: number, b: number, c: any, d: number, e: number, f: number, g: number) => [number, number];
+export const composite_layers: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number, l: number, m: number, n: number, o: number, p: number, q: number) => [number, number, number, number];
+export const create_opacity_mask: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+export const fill_rects: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number) => [number, number];
+export const flip_pixels_vertically: (a: number, b: number, c: any, d: number, e: number) => [number, number];
+export const mask_to_rects: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+export const select_layer_opacity: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number, number, number];
+export const auto_select_region_mask: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number, number, number];
+export const dither_to_palette: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number) => [number, number];
+export const stroke_selection: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number, l: number, m: number) => [number, number];
+export const fill_lasso_selection: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number) => [number, number, number];
+export const fill_lasso_selection_point_in_polygon: (a: number, b: number, c: any, d: number, e: number, f: number, g: number) => [number, number, number];
+export const fill_lasso_selection_with_mask: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number) => [number, number, number];
+export const __wbg_layertree_free: (a: number, b: number) => void;
+export const content_bounds: (a: number, b: number, c: number, d: number, e: number) => [number, number, number, number];
+export const content_bounds_of_layers: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number, number];
+export const layertree_begin_group: (a: number, b: number, c: number, d: number, e: number) => [number, number];
+export const layertree_end_group: (a: number) => [number, number];
+export const layertree_flatten: (a: number) => [number, number, number, number];
+export const layertree_new: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number];
+export const layertree_push_layer: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number, j: number) => [number, number];
+export const export_svg_rects: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number, number];
+export const export_svg_traced: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number, number];
+export const box_blur: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number, l: number) => [number, number];
+export const gaussian_blur: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number, l: number) => [number, number];
+export const apply_mask_offset: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number, number, number];
+export const combine_masks_add: (a: number, b: number, c: number, d: number, e: number) => [number, number, number, number];
 export const combine_masks_replace: (a: number, b: number) => [number, number];
-export const fill_rect_mask: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number) => void;
-export const apply_mask_offset: (a: number, b: number, c: number, d: number, e: number, f: number) => [number, number];
-export const create_opacity_mask: (a: number, b: number, c: number, d: number) => [number, number];
-export const auto_select_region_mask: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number];
-export const fill_lasso_selection: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number) => number;
-export const fill_lasso_selection_with_mask: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number) => number;
-export const fill_lasso_selection_point_in_polygon: (a: number, b: number, c: any, d: number, e: number, f: number, g: number) => number;
-export const __wbindgen_export_0: WebAssembly.Table;
-export const __wbindgen_malloc: (a: number, b: number) => number;
+export const combine_masks_subtract: (a: number, b: number, c: number, d: number, e: number) => [number, number, number, number];
+export const composite_fill_result: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number) => [number, number, number, number];
+export const extract_region: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number) => [number, number, number, number];
+export const fill_rect_mask: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number) => [number, number];
+export const filter_by_selection_mask: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number, number, number];
+export const paste_region: (a: number, b: number, c: any, d: number, e: number, f: number, g: number, h: number, i: number, j: number, k: number, l: number, m: number, n: number, o: number) => [number, number];
+export const preserve_alpha: (a: number, b: number, c: any, d: number, e: number, f: number, g: number) => [number, number];
+export const rects_to_mask: (a: number, b: number, c: number, d: number) => [number, number, number, number];
+export const trim_mask_with_box: (a: number, b: number, c: number, d: number, e: number, f: number, g: number, h: number) => [number, number, number, number];
+export const __wbindgen_externrefs: WebAssembly.Table;
 export const __wbindgen_free: (a: number, b: number, c: number) => void;
-export const __wbindgen_realloc: (a: number, b: number, c: number, d: number) => number;
+export const __wbindgen_malloc: (a: number, b: number) => number;
+export const __externref_table_dealloc: (a: number) => void;
 export const __wbindgen_start: () => void;
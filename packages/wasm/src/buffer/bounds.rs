@@ -0,0 +1,144 @@
+use wasm_bindgen::prelude::*;
+
+use crate::utils::rect::Rect;
+use crate::utils::validate::{check_layers_len, check_rgba_len};
+
+/// アルファがしきい値を超えるピクセルを囲む最小の矩形を求める（トリミング用）
+/// alpha_threshold: この値より大きいアルファを内容とみなす（0 なら create_opacity_mask と同じ判定）
+/// 戻り値は [x, y, width, height]。内容がなければ空配列
+#[wasm_bindgen]
+pub fn content_bounds(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    alpha_threshold: u8,
+) -> Result<Vec<u32>, JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+
+    let w = width as usize;
+    let bounds = find_content_bounds(width, height, |x, y| {
+        buffer[(y * w + x) * 4 + 3] > alpha_threshold
+    });
+    Ok(bounds_to_vec(bounds))
+}
+
+/// 複数レイヤーをまとめた内容の範囲を求める
+/// layers: 同じサイズのRGBAバッファを layer_count 枚連結したもの
+/// 戻り値は content_bounds と同じ
+#[wasm_bindgen]
+pub fn content_bounds_of_layers(
+    layers: &[u8],
+    width: u32,
+    height: u32,
+    layer_count: u32,
+    alpha_threshold: u8,
+) -> Result<Vec<u32>, JsError> {
+    let layer_len = check_layers_len("layers", layers, width, height, layer_count)?;
+
+    let w = width as usize;
+    let bounds = find_content_bounds(width, height, |x, y| {
+        let alpha_index = (y * w + x) * 4 + 3;
+        (0..layer_count as usize).any(|l| layers[l * layer_len + alpha_index] > alpha_threshold)
+    });
+    Ok(bounds_to_vec(bounds))
+}
+
+/// is_content が真になるピクセルを囲む最小の矩形（Rust側から使うためのAPI）
+///
+/// 上下は行単位、左右は列単位で外側から走査し、最初に内容が見つかった所で打ち切るので、
+/// 内容が大きいほど見るピクセルが少なくなる。マスクは確保しない
+pub fn find_content_bounds<F>(width: u32, height: u32, is_content: F) -> Option<Rect>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let w = width as usize;
+    let h = height as usize;
+
+    let row_has = |y: usize| (0..w).any(|x| is_content(x, y));
+
+    // 上端（見つからなければ内容なし）
+    let top = (0..h).find(|&y| row_has(y))?;
+    // 下端
+    let bottom = (top..h).rev().find(|&y| row_has(y))?;
+
+    // 左端・右端は上下端の間の行だけを見る
+    let col_has = |x: usize| (top..=bottom).any(|y| is_content(x, y));
+    let left = (0..w).find(|&x| col_has(x))?;
+    let right = (left..w).rev().find(|&x| col_has(x))?;
+
+    Some(Rect {
+        x: left as u32,
+        y: top as u32,
+        width: (right - left + 1) as u32,
+        height: (bottom - top + 1) as u32,
+    })
+}
+
+fn bounds_to_vec(bounds: Option<Rect>) -> Vec<u32> {
+    match bounds {
+        Some(r) => vec![r.x, r.y, r.width, r.height],
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 指定したピクセルだけにアルファを入れた RGBA
+    fn with_alpha(width: u32, height: u32, pixels: &[(usize, usize, u8)]) -> Vec<u8> {
+        let mut buffer = vec![0u8; (width * height * 4) as usize];
+        for &(x, y, a) in pixels {
+            buffer[(y * width as usize + x) * 4 + 3] = a;
+        }
+        buffer
+    }
+
+    #[test]
+    fn empty_buffer_has_no_bounds() {
+        assert!(
+            content_bounds(&with_alpha(4, 3, &[]), 4, 3, 0)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(content_bounds(&[], 0, 0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn single_pixel_is_its_own_bounds() {
+        let buffer = with_alpha(5, 4, &[(3, 2, 255)]);
+        assert_eq!(content_bounds(&buffer, 5, 4, 0).unwrap(), [3, 2, 1, 1]);
+    }
+
+    #[test]
+    fn bounds_cover_the_extreme_pixels() {
+        let buffer = with_alpha(6, 5, &[(1, 3, 10), (4, 1, 10), (2, 4, 10)]);
+        assert_eq!(content_bounds(&buffer, 6, 5, 0).unwrap(), [1, 1, 4, 4]);
+    }
+
+    #[test]
+    fn alpha_at_the_threshold_is_not_content() {
+        let buffer = with_alpha(4, 1, &[(0, 0, 100), (3, 0, 101)]);
+        assert_eq!(content_bounds(&buffer, 4, 1, 100).unwrap(), [3, 0, 1, 1]);
+        assert_eq!(content_bounds(&buffer, 4, 1, 99).unwrap(), [0, 0, 4, 1]);
+        assert!(content_bounds(&buffer, 4, 1, 255).unwrap().is_empty());
+    }
+
+    #[test]
+    fn layer_bounds_are_the_union() {
+        let layers = [
+            with_alpha(4, 4, &[(0, 1, 255)]),
+            with_alpha(4, 4, &[(2, 3, 255)]),
+        ]
+        .concat();
+        assert_eq!(
+            content_bounds_of_layers(&layers, 4, 4, 2, 0).unwrap(),
+            [0, 1, 3, 3]
+        );
+        assert!(
+            content_bounds_of_layers(&layers, 4, 4, 2, 255)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,6 @@
+// 矩形領域の切り出し・貼り付け
+pub mod region;
+// 内容の範囲（トリミング用）
+pub mod bounds;
+// 矩形への分解と矩形単位の塗りつぶし・消去
+pub mod rects;
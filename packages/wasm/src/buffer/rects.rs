@@ -0,0 +1,210 @@
+use wasm_bindgen::prelude::*;
+
+use crate::utils::rect::{Rect, rects_from_flat};
+use crate::utils::validate::{check_mask_len, check_rgba_len};
+
+/// 選択範囲マスクを軸平行な矩形の集合に分解（貪欲法）
+/// 戻り値は [x, y, width, height, x, y, width, height, ...]
+/// 矩形に近い選択範囲なら少数の RectFragment として履歴に残したり、
+/// fill_rects / clear_rects で行単位の操作にしたりできる
+#[wasm_bindgen]
+pub fn mask_to_rects(mask: &[u8], width: u32, height: u32) -> Result<Vec<u32>, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+
+    Ok(decompose_mask_rects(mask, width, height)
+        .into_iter()
+        .flat_map(|r| [r.x, r.y, r.width, r.height])
+        .collect())
+}
+
+/// マスクを重なりのない矩形の列に分解する（Rust側から使うためのAPI）
+///
+/// 上から、左から走査し、未処理の選択ピクセルを見つけたら右へ、次に下へ伸ばせるだけ伸ばす。
+/// 最小分解ではないが、各ピクセルを定数回しか見ないので面積に対して線形
+pub fn decompose_mask_rects(mask: &[u8], width: u32, height: u32) -> Vec<Rect> {
+    let w = width as usize;
+    let h = height as usize;
+    let mut covered = vec![false; w * h];
+    let mut rects = Vec::new();
+
+    let is_open = |covered: &[bool], idx: usize| -> bool {
+        idx < mask.len() && mask[idx] != 0 && !covered[idx]
+    };
+
+    for y in 0..h {
+        let mut x = 0;
+        while x < w {
+            if !is_open(&covered, y * w + x) {
+                x += 1;
+                continue;
+            }
+
+            // 右へ伸ばす
+            let mut rw = 1;
+            while x + rw < w && is_open(&covered, y * w + x + rw) {
+                rw += 1;
+            }
+
+            // 行全体が埋まっている限り下へ伸ばす
+            let mut rh = 1;
+            while y + rh < h {
+                let row = (y + rh) * w + x;
+                if (row..row + rw).all(|idx| is_open(&covered, idx)) {
+                    rh += 1;
+                } else {
+                    break;
+                }
+            }
+
+            for ry in y..y + rh {
+                covered[ry * w + x..ry * w + x + rw].fill(true);
+            }
+
+            rects.push(Rect {
+                x: x as u32,
+                y: y as u32,
+                width: rw as u32,
+                height: rh as u32,
+            });
+            x += rw;
+        }
+    }
+
+    rects
+}
+
+/// 矩形の列の内側を指定色で塗りつぶす（合成せずに置き換える）
+/// rects: mask_to_rects 形式の [x, y, width, height, ...]。画像からはみ出した部分は無視する
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn fill_rects(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rects: &[u32],
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+) -> Result<(), JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    let rects = rects_from_flat("rects", rects)?;
+
+    let w = width as usize;
+    let color = [r, g, b, a];
+
+    for rect in &rects {
+        let Some((x0, y0, x1, y1)) = rect.clip_to(width, height) else {
+            continue;
+        };
+        for y in y0..y1 {
+            for px in buffer[(y * w + x0) * 4..(y * w + x1) * 4].chunks_exact_mut(4) {
+                px.copy_from_slice(&color);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// 矩形の列の内側を透明にする
+/// rects: mask_to_rects 形式の [x, y, width, height, ...]。画像からはみ出した部分は無視する
+#[wasm_bindgen]
+pub fn clear_rects(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    rects: &[u32],
+) -> Result<(), JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    let rects = rects_from_flat("rects", rects)?;
+
+    let w = width as usize;
+
+    for rect in &rects {
+        let Some((x0, y0, x1, y1)) = rect.clip_to(width, height) else {
+            continue;
+        };
+        for y in y0..y1 {
+            buffer[(y * w + x0) * 4..(y * w + x1) * 4].fill(0);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::selection::mask_ops::rects_to_mask;
+
+    // 決まった並びの 0/1 マスク（穴や飛び地を含む）
+    fn pattern(width: u32, height: u32) -> Vec<u8> {
+        (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                ((x * 7 + y * 3) % 5 < 3 || (2..5).contains(&x)) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rects_cover_the_mask_without_overlap() {
+        let (width, height) = (11, 9);
+        let mask = pattern(width, height);
+        let mut hits = vec![0u8; mask.len()];
+        for r in decompose_mask_rects(&mask, width, height) {
+            for y in r.y..r.y + r.height {
+                for x in r.x..r.x + r.width {
+                    hits[(y * width + x) as usize] += 1;
+                }
+            }
+        }
+        assert_eq!(hits, mask);
+    }
+
+    #[test]
+    fn mask_round_trip_is_stable() {
+        let (width, height) = (11, 9);
+        let mask = pattern(width, height);
+        let rects = mask_to_rects(&mask, width, height).unwrap();
+        let restored = rects_to_mask(&rects, width, height).unwrap();
+        assert_eq!(restored, mask);
+        // 2周目も同じ矩形になる
+        assert_eq!(mask_to_rects(&restored, width, height).unwrap(), rects);
+    }
+
+    #[test]
+    fn rects_round_trip_is_stable() {
+        // 重なりのない矩形は分解し直しても変わらない
+        let rects = [0, 0, 3, 2, 4, 0, 1, 4, 0, 3, 2, 1];
+        let mask = rects_to_mask(&rects, 6, 4).unwrap();
+        assert_eq!(mask_to_rects(&mask, 6, 4).unwrap(), rects);
+    }
+
+    #[test]
+    fn decompose_extends_right_then_down() {
+        let mask = [
+            1, 1, 0, //
+            1, 1, 1, //
+            0, 1, 1,
+        ];
+        assert_eq!(
+            mask_to_rects(&mask, 3, 3).unwrap(),
+            [0, 0, 2, 2, 2, 1, 1, 2, 1, 2, 1, 1]
+        );
+        assert!(mask_to_rects(&[0; 4], 2, 2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn fill_and_clear_clip_to_the_buffer() {
+        let mut buffer = vec![9u8; 3 * 2 * 4];
+        fill_rects(&mut buffer, 3, 2, &[2, 1, 5, 5], 1, 2, 3, 4).unwrap();
+        assert_eq!(&buffer[20..], &[1, 2, 3, 4]);
+        assert!(buffer[..20].iter().all(|&v| v == 9));
+
+        clear_rects(&mut buffer, 3, 2, &[0, 0, 2, 1, 9, 9, 1, 1]).unwrap();
+        assert!(buffer[..8].iter().all(|&v| v == 0));
+        assert!(buffer[8..20].iter().all(|&v| v == 9));
+    }
+}
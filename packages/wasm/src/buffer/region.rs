@@ -0,0 +1,410 @@
+use wasm_bindgen::prelude::*;
+
+use crate::utils::composite::blend_source_over;
+use crate::utils::mask::MaskFormat;
+use crate::utils::validate::{check_mask_len, check_rgba_len, pixel_count};
+
+/// 貼り付け時の合成方法
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PasteBlendMode {
+    /// 貼り付け先のピクセルを置き換える
+    Replace = 0,
+    /// 貼り付け先に source-over で重ねる
+    SourceOver = 1,
+}
+
+/// RGBAバッファから矩形領域を切り出す
+/// buffer: 元のピクセルバッファ (RGBA, width x height)
+/// rect_x, rect_y, rect_width, rect_height: 切り出す矩形（キャンバス外にはみ出してもよい）
+/// mask: 省略可。キャンバスと同じサイズの選択範囲マスク。選択範囲外のピクセルは透明になり、
+///       被覆率が中間の値ならアルファに被覆率を掛ける
+/// mask_format: mask の形式
+///
+/// 戻り値は rect_width x rect_height のRGBA。キャンバス外の部分は透明
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn extract_region(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    rect_x: i32,
+    rect_y: i32,
+    rect_width: u32,
+    rect_height: u32,
+    mask: Option<Vec<u8>>,
+    mask_format: MaskFormat,
+) -> Result<Vec<u8>, JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    if let Some(mask) = &mask {
+        check_mask_len("mask", mask, width, height)?;
+    }
+
+    let out_len = pixel_count(rect_width, rect_height)?
+        .checked_mul(4)
+        .ok_or_else(|| JsError::new("region is too large"))?;
+    let mut result = vec![0u8; out_len];
+
+    let w = width as usize;
+    let rw = rect_width as usize;
+    let Some(span) = clip_span(rect_x, rect_y, rect_width, rect_height, width, height) else {
+        return Ok(result);
+    };
+
+    for y in span.y0..span.y1 {
+        let src_y = (y as i64 + rect_y as i64) as usize;
+        let src_row = src_y * w;
+        let dst_row = y * rw;
+
+        for x in span.x0..span.x1 {
+            let src_x = (x as i64 + rect_x as i64) as usize;
+            let coverage = match &mask {
+                Some(mask) => mask_format.coverage(mask[src_row + src_x]),
+                None => 255,
+            };
+            if coverage == 0 {
+                continue;
+            }
+
+            let s = (src_row + src_x) * 4;
+            let d = (dst_row + x) * 4;
+            result[d..d + 4].copy_from_slice(&buffer[s..s + 4]);
+            if coverage < 255 {
+                result[d + 3] = ((result[d + 3] as u32 * coverage as u32 + 127) / 255) as u8;
+            }
+        }
+    }
+
+    Ok(result)
+}
+
+/// RGBAバッファに別のRGBAバッファを貼り付ける
+/// dst: 貼り付け先 (RGBA, width x height)
+/// src: 貼り付けるバッファ (RGBA, src_width x src_height)
+/// x, y: 貼り付け位置（負の値やキャンバス外へのはみ出しは切り取られる）
+/// blend: 合成方法
+/// mask: 省略可。src と同じサイズのマスク。被覆率に応じて src を適用する
+/// mask_format: mask の形式
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn paste_region(
+    dst: &mut [u8],
+    width: u32,
+    height: u32,
+    src: &[u8],
+    src_width: u32,
+    src_height: u32,
+    x: i32,
+    y: i32,
+    blend: PasteBlendMode,
+    mask: Option<Vec<u8>>,
+    mask_format: MaskFormat,
+) -> Result<(), JsError> {
+    check_rgba_len("dst", dst, width, height)?;
+    check_rgba_len("src", src, src_width, src_height)?;
+    if let Some(mask) = &mask {
+        check_mask_len("mask", mask, src_width, src_height)?;
+    }
+
+    let w = width as usize;
+    let sw = src_width as usize;
+    // src 座標系で見たときの dst の範囲
+    let Some(span) = clip_span(x, y, src_width, src_height, width, height) else {
+        return Ok(());
+    };
+
+    for sy in span.y0..span.y1 {
+        let dst_y = (sy as i64 + y as i64) as usize;
+
+        for sx in span.x0..span.x1 {
+            let dst_x = (sx as i64 + x as i64) as usize;
+            let si = sy * sw + sx;
+            let coverage = match &mask {
+                Some(mask) => mask_format.coverage(mask[si]),
+                None => 255,
+            };
+            if coverage == 0 {
+                continue;
+            }
+
+            let s = si * 4;
+            let d = (dst_y * w + dst_x) * 4;
+            let color = [src[s], src[s + 1], src[s + 2], src[s + 3]];
+            match blend {
+                PasteBlendMode::Replace => {
+                    replace_with_coverage(&mut dst[d..d + 4], color, coverage);
+                }
+                PasteBlendMode::SourceOver => {
+                    blend_source_over(&mut dst[d..d + 4], color, coverage);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// 置き換えを被覆率で補間する（被覆率 255 なら完全な置き換え）
+fn replace_with_coverage(dst: &mut [u8], src: [u8; 4], coverage: u8) {
+    if coverage == 255 {
+        dst.copy_from_slice(&src);
+        return;
+    }
+
+    let t = coverage as f32 / 255.0;
+    let sa = src[3] as f32 / 255.0;
+    let da = dst[3] as f32 / 255.0;
+    let out_a = da + (sa - da) * t;
+    if out_a <= 0.0 {
+        dst.fill(0);
+        return;
+    }
+
+    // 色はアルファで重み付けして補間する（透明ピクセルの色が混ざらないように）
+    for c in 0..3 {
+        let value = (dst[c] as f32 * da * (1.0 - t) + src[c] as f32 * sa * t) / out_a;
+        dst[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+    dst[3] = (out_a * 255.0).round().clamp(0.0, 255.0) as u8;
+}
+
+// 矩形（ローカル座標）のうちキャンバス内に入る範囲
+struct Span {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+/// 原点 (offset_x, offset_y) に置いた rect_width x rect_height の矩形のうち、
+/// canvas_width x canvas_height に収まる部分を矩形のローカル座標で返す
+fn clip_span(
+    offset_x: i32,
+    offset_y: i32,
+    rect_width: u32,
+    rect_height: u32,
+    canvas_width: u32,
+    canvas_height: u32,
+) -> Option<Span> {
+    let clip = |offset: i32, len: u32, canvas: u32| -> (usize, usize) {
+        let start = (-(offset as i64)).clamp(0, len as i64);
+        let end = (canvas as i64 - offset as i64).clamp(0, len as i64);
+        (start as usize, end as usize)
+    };
+
+    let (x0, x1) = clip(offset_x, rect_width, canvas_width);
+    let (y0, y1) = clip(offset_y, rect_height, canvas_height);
+    if x0 >= x1 || y0 >= y1 {
+        return None;
+    }
+    Some(Span { x0, y0, x1, y1 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 3x3 で i 番目のピクセルが [i, i, i, 255]
+    fn numbered() -> Vec<u8> {
+        (0..9u8).flat_map(|i| [i, i, i, 255]).collect()
+    }
+
+    fn values(buffer: &[u8]) -> Vec<Option<u8>> {
+        buffer
+            .chunks(4)
+            .map(|px| (px[3] != 0).then_some(px[0]))
+            .collect()
+    }
+
+    fn extract(x: i32, y: i32, w: u32, h: u32) -> Vec<Option<u8>> {
+        values(&extract_region(&numbered(), 3, 3, x, y, w, h, None, MaskFormat::Binary).unwrap())
+    }
+
+    #[test]
+    fn extract_copies_the_rect() {
+        assert_eq!(extract(1, 1, 2, 2), [Some(4), Some(5), Some(7), Some(8)]);
+    }
+
+    #[test]
+    fn extract_clips_to_the_canvas() {
+        assert_eq!(extract(-1, -1, 2, 2), [None, None, None, Some(0)]);
+        assert_eq!(extract(2, 1, 2, 1), [Some(5), None]);
+    }
+
+    #[test]
+    fn extract_far_outside_is_transparent() {
+        for (x, y) in [(i32::MAX, 0), (0, i32::MAX), (i32::MIN, i32::MIN), (3, 0)] {
+            assert_eq!(extract(x, y, 2, 2), [None; 4]);
+        }
+    }
+
+    #[test]
+    fn extract_applies_mask_coverage() {
+        let mask = vec![0, 128, 255, 0, 0, 0, 0, 0, 0];
+        let coverage = extract_region(
+            &numbered(),
+            3,
+            3,
+            0,
+            0,
+            3,
+            1,
+            Some(mask.clone()),
+            MaskFormat::Coverage,
+        )
+        .unwrap();
+        assert_eq!(coverage, [0, 0, 0, 0, 1, 1, 1, 128, 2, 2, 2, 255]);
+        let binary = extract_region(
+            &numbered(),
+            3,
+            3,
+            0,
+            0,
+            3,
+            1,
+            Some(mask),
+            MaskFormat::Binary,
+        )
+        .unwrap();
+        assert_eq!(binary, [0, 0, 0, 0, 1, 1, 1, 255, 2, 2, 2, 255]);
+    }
+
+    fn paste(x: i32, y: i32) -> Vec<Option<u8>> {
+        let mut dst = numbered();
+        let src = [[100, 100, 100, 255], [101, 101, 101, 255]].concat();
+        paste_region(
+            &mut dst,
+            3,
+            3,
+            &src,
+            2,
+            1,
+            x,
+            y,
+            PasteBlendMode::Replace,
+            None,
+            MaskFormat::Binary,
+        )
+        .unwrap();
+        values(&dst)
+    }
+
+    #[test]
+    fn paste_clips_negative_offsets() {
+        let mut expected: Vec<Option<u8>> = (0..9).map(Some).collect();
+        expected[3] = Some(101);
+        assert_eq!(paste(-1, 1), expected);
+    }
+
+    #[test]
+    fn paste_clips_past_the_right_edge() {
+        let mut expected: Vec<Option<u8>> = (0..9).map(Some).collect();
+        expected[8] = Some(100);
+        assert_eq!(paste(2, 2), expected);
+    }
+
+    #[test]
+    fn paste_far_outside_changes_nothing() {
+        let expected: Vec<Option<u8>> = (0..9).map(Some).collect();
+        for (x, y) in [
+            (i32::MAX, 0),
+            (0, i32::MAX),
+            (i32::MIN, 0),
+            (i32::MAX, i32::MAX),
+        ] {
+            assert_eq!(paste(x, y), expected);
+        }
+    }
+
+    #[test]
+    fn paste_blends_by_coverage() {
+        let src = [0, 0, 0, 255];
+        let mut dst = [200, 200, 200, 255];
+        paste_region(
+            &mut dst,
+            1,
+            1,
+            &src,
+            1,
+            1,
+            0,
+            0,
+            PasteBlendMode::Replace,
+            Some(vec![0]),
+            MaskFormat::Coverage,
+        )
+        .unwrap();
+        assert_eq!(dst, [200, 200, 200, 255]);
+
+        paste_region(
+            &mut dst,
+            1,
+            1,
+            &src,
+            1,
+            1,
+            0,
+            0,
+            PasteBlendMode::Replace,
+            Some(vec![51]),
+            MaskFormat::Coverage,
+        )
+        .unwrap();
+        assert_eq!(dst, [160, 160, 160, 255]);
+
+        // 透明な src で置き換えると透明になり、被覆率が中間なら色を変えずにアルファだけ下がる
+        let mut dst = [200, 100, 50, 255];
+        paste_region(
+            &mut dst,
+            1,
+            1,
+            &[0, 0, 0, 0],
+            1,
+            1,
+            0,
+            0,
+            PasteBlendMode::Replace,
+            Some(vec![255]),
+            MaskFormat::Coverage,
+        )
+        .unwrap();
+        assert_eq!(dst, [0, 0, 0, 0]);
+        let mut dst = [200, 100, 50, 255];
+        paste_region(
+            &mut dst,
+            1,
+            1,
+            &[0, 0, 0, 0],
+            1,
+            1,
+            0,
+            0,
+            PasteBlendMode::Replace,
+            Some(vec![128]),
+            MaskFormat::Coverage,
+        )
+        .unwrap();
+        assert_eq!(dst, [200, 100, 50, 127]);
+    }
+
+    #[test]
+    fn source_over_paste_keeps_the_destination_under_transparent_pixels() {
+        let mut dst = numbered();
+        let src = [[0, 0, 0, 0], [100, 100, 100, 255]].concat();
+        paste_region(
+            &mut dst,
+            3,
+            3,
+            &src,
+            2,
+            1,
+            0,
+            0,
+            PasteBlendMode::SourceOver,
+            None,
+            MaskFormat::Binary,
+        )
+        .unwrap();
+        assert_eq!(&values(&dst)[..3], [Some(0), Some(100), Some(2)]);
+    }
+}
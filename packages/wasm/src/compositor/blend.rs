@@ -0,0 +1,295 @@
+use wasm_bindgen::prelude::*;
+
+/// レイヤーのブレンドモード
+/// 0-7 はアプリ側の blendModeIds（features/layer/model.ts）と同じで、blend.frag.glsl もこの値で分岐する
+/// 8 以降は W3C Compositing and Blending の式による拡張モード（シェーダーは 13 の LinearDodge を blendAdd で扱う）
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerBlendMode {
+    Normal = 0,
+    Multiply = 1,
+    Screen = 2,
+    Overlay = 3,
+    SoftLight = 4,
+    HardLight = 5,
+    LinearLight = 6,
+    VividLight = 7,
+    Darken = 8,
+    Lighten = 9,
+    ColorDodge = 10,
+    ColorBurn = 11,
+    LinearBurn = 12,
+    LinearDodge = 13,
+    Difference = 14,
+    Exclusion = 15,
+    Subtract = 16,
+    Divide = 17,
+    Hue = 18,
+    Saturation = 19,
+    Color = 20,
+    Luminosity = 21,
+}
+
+impl LayerBlendMode {
+    /// blendModeIds の数値から変換する（未知の値は None）
+    pub fn from_id(id: u8) -> Option<Self> {
+        Some(match id {
+            0 => Self::Normal,
+            1 => Self::Multiply,
+            2 => Self::Screen,
+            3 => Self::Overlay,
+            4 => Self::SoftLight,
+            5 => Self::HardLight,
+            6 => Self::LinearLight,
+            7 => Self::VividLight,
+            8 => Self::Darken,
+            9 => Self::Lighten,
+            10 => Self::ColorDodge,
+            11 => Self::ColorBurn,
+            12 => Self::LinearBurn,
+            13 => Self::LinearDodge,
+            14 => Self::Difference,
+            15 => Self::Exclusion,
+            16 => Self::Subtract,
+            17 => Self::Divide,
+            18 => Self::Hue,
+            19 => Self::Saturation,
+            20 => Self::Color,
+            21 => Self::Luminosity,
+            _ => return None,
+        })
+    }
+
+    /// RGB の合成色。src, dst はどちらも 0.0-1.0
+    #[inline]
+    pub fn blend_color(self, src: [f32; 3], dst: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::Hue => set_lum(set_sat(src, sat(dst)), lum(dst)),
+            Self::Saturation => set_lum(set_sat(dst, sat(src)), lum(dst)),
+            Self::Color => set_lum(src, lum(dst)),
+            Self::Luminosity => set_lum(dst, lum(src)),
+            _ => [0, 1, 2].map(|c| self.blend_channel(src[c], dst[c])),
+        }
+    }
+
+    /// 1チャンネル分の合成色。src, dst はどちらも 0.0-1.0
+    /// 0-7 は blend.frag.glsl の blendXxxRGB と同じ式
+    /// 色相・彩度などのモードはチャンネル単位では決まらないので blend_color を使うこと（ここでは src を返す）
+    #[inline]
+    pub fn blend_channel(self, src: f32, dst: f32) -> f32 {
+        match self {
+            Self::Normal => src,
+            Self::Multiply => src * dst,
+            Self::Screen => 1.0 - (1.0 - src) * (1.0 - dst),
+            Self::Overlay => overlay(src, dst),
+            Self::SoftLight => {
+                if dst < 0.5 {
+                    2.0 * src * dst + src * src * (1.0 - 2.0 * dst)
+                } else {
+                    src.sqrt() * (2.0 * dst - 1.0) + 2.0 * src * (1.0 - dst)
+                }
+            }
+            Self::HardLight => overlay(dst, src),
+            Self::LinearLight => (src + 2.0 * dst - 1.0).clamp(0.0, 1.0),
+            Self::VividLight => {
+                let value = if src < 0.5 {
+                    1.0 - (1.0 - dst) / (2.0 * src).max(1e-5)
+                } else {
+                    dst / (2.0 * (1.0 - src)).max(1e-5)
+                };
+                value.clamp(0.0, 1.0)
+            }
+            Self::Darken => src.min(dst),
+            Self::Lighten => src.max(dst),
+            Self::ColorDodge => {
+                if dst == 0.0 {
+                    0.0
+                } else if src >= 1.0 {
+                    1.0
+                } else {
+                    (dst / (1.0 - src)).min(1.0)
+                }
+            }
+            Self::ColorBurn => {
+                if dst >= 1.0 {
+                    1.0
+                } else if src == 0.0 {
+                    0.0
+                } else {
+                    1.0 - ((1.0 - dst) / src).min(1.0)
+                }
+            }
+            Self::LinearBurn => (src + dst - 1.0).max(0.0),
+            Self::LinearDodge => (src + dst).min(1.0),
+            Self::Difference => (dst - src).abs(),
+            Self::Exclusion => src + dst - 2.0 * src * dst,
+            Self::Subtract => (dst - src).max(0.0),
+            Self::Divide => {
+                if src == 0.0 {
+                    if dst == 0.0 { 0.0 } else { 1.0 }
+                } else {
+                    (dst / src).min(1.0)
+                }
+            }
+            Self::Hue | Self::Saturation | Self::Color | Self::Luminosity => src,
+        }
+    }
+}
+
+// シェーダーの mix(..., step(0.5, dst)) をそのまま分岐にしたもの
+#[inline]
+fn overlay(src: f32, dst: f32) -> f32 {
+    if dst < 0.5 {
+        2.0 * src * dst
+    } else {
+        1.0 - 2.0 * (1.0 - src) * (1.0 - dst)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 非分離モード（W3C Compositing and Blending Level 1 の Lum / SetLum / Sat / SetSat）
+// ---------------------------------------------------------------------------
+
+#[inline]
+fn lum(c: [f32; 3]) -> f32 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+// 輝度を保ったまま 0.0-1.0 に収める
+#[inline]
+fn clip_color(c: [f32; 3]) -> [f32; 3] {
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    let mut out = c;
+    if n < 0.0 {
+        out = out.map(|v| l + (v - l) * l / (l - n));
+    }
+    if x > 1.0 {
+        out = out.map(|v| l + (v - l) * (1.0 - l) / (x - l));
+    }
+    out
+}
+
+#[inline]
+fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(c);
+    clip_color(c.map(|v| v + d))
+}
+
+#[inline]
+fn sat(c: [f32; 3]) -> f32 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+// 最大・中間・最小の並びを保ったまま彩度（最大 - 最小）を s にする
+#[inline]
+fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+    let mut idx = [0, 1, 2];
+    idx.sort_by(|&a, &b| c[a].total_cmp(&c[b]));
+    let [min, mid, max] = idx;
+
+    let mut out = [0.0; 3];
+    if c[max] > c[min] {
+        out[mid] = (c[mid] - c[min]) * s / (c[max] - c[min]);
+        out[max] = s;
+    }
+    out
+}
+
+/// src を dst の上に重ねる（blend.frag.glsl の blendXxx と同じ式）
+/// 色は 0.0-1.0 の RGBA。src には不透明度を掛け済みであること（シェーダーは RGB にも掛ける）
+#[inline]
+pub fn blend_pixel(src: [f32; 4], dst: [f32; 4], mode: LayerBlendMode) -> [f32; 4] {
+    let sa = src[3];
+    let blended = mode.blend_color([src[0], src[1], src[2]], [dst[0], dst[1], dst[2]]);
+    let mut out = [0.0; 4];
+    for c in 0..3 {
+        out[c] = dst[c] * (1.0 - sa) + blended[c] * sa;
+    }
+    out[3] = sa + dst[3] * (1.0 - sa);
+    out
+}
+
+/// src を dst の上に重ねる（W3C Compositing and Blending の source-over）
+/// 色は 0.0-1.0 の RGBA で、RGB にアルファを掛けていない値。linear light の合成で使う
+///
+/// blend_pixel と違って RGB に不透明度を掛けないので、半透明の境界が暗くならない。
+/// 戻り値も RGB にアルファを掛けていない値（完全に透明なら 0）
+#[inline]
+pub fn blend_pixel_straight(src: [f32; 4], dst: [f32; 4], mode: LayerBlendMode) -> [f32; 4] {
+    let sa = src[3];
+    let da = dst[3];
+    let out_a = sa + da * (1.0 - sa);
+    if out_a <= 0.0 {
+        return [0.0; 4];
+    }
+
+    let blended = mode.blend_color([src[0], src[1], src[2]], [dst[0], dst[1], dst[2]]);
+    let mut out = [0.0; 4];
+    for c in 0..3 {
+        let premul = src[c] * sa * (1.0 - da) + dst[c] * da * (1.0 - sa) + blended[c] * sa * da;
+        out[c] = premul / out_a;
+    }
+    out[3] = out_a;
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 不透明な src を不透明な dst に重ね、フレームバッファと同じく 8bit に丸める
+    fn blend_bytes(mode: LayerBlendMode, src: [u8; 3], dst: [u8; 3]) -> [u8; 3] {
+        let to_f = |c: [u8; 3]| [c[0], c[1], c[2], 255].map(|v| v as f32 / 255.0);
+        let out = blend_pixel(to_f(src), to_f(dst), mode);
+        [0, 1, 2].map(|c| (out[c].clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    #[test]
+    fn each_mode_matches_reference_bytes() {
+        let src = [200, 100, 50];
+        let dst = [64, 128, 192];
+        // 期待値は blend.frag.glsl / W3C の式を倍精度で計算して丸めたもの
+        let expected: [[u8; 3]; 22] = [
+            [200, 100, 50],
+            [50, 50, 38],
+            [214, 178, 204],
+            [100, 101, 154],
+            [179, 100, 82],
+            [173, 100, 75],
+            [73, 101, 179],
+            [148, 93, 94],
+            [64, 100, 50],
+            [200, 128, 192],
+            [255, 211, 239],
+            [11, 0, 0],
+            [9, 0, 0],
+            [255, 228, 242],
+            [136, 28, 142],
+            [164, 128, 167],
+            [0, 28, 142],
+            [82, 255, 255],
+            [180, 95, 52],
+            [55, 130, 205],
+            [191, 91, 41],
+            [73, 137, 201],
+        ];
+
+        for (id, want) in expected.into_iter().enumerate() {
+            let mode = LayerBlendMode::from_id(id as u8).unwrap();
+            assert_eq!(blend_bytes(mode, src, dst), want, "{mode:?}");
+        }
+        assert_eq!(LayerBlendMode::from_id(22), None);
+    }
+
+    #[test]
+    fn transparent_src_keeps_dst() {
+        let dst = [0.25, 0.5, 0.75, 1.0];
+        for id in 0..22 {
+            let mode = LayerBlendMode::from_id(id).unwrap();
+            let src = [0.8, 0.4, 0.2, 0.0];
+            assert_eq!(blend_pixel(src, dst, mode), dst, "{mode:?}");
+        }
+    }
+}
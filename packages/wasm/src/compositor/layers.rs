@@ -0,0 +1,375 @@
+use wasm_bindgen::prelude::*;
+
+use crate::compositor::blend::{LayerBlendMode, blend_pixel, blend_pixel_straight};
+use crate::utils::color::{linear_to_srgb_byte, srgb_byte_to_linear, srgb_to_linear};
+use crate::utils::validate::{check_layers_len, pixel_count};
+
+/// 合成に使うレイヤーの属性
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayerProps {
+    pub mode: LayerBlendMode,
+    pub opacity: f32,
+    pub enabled: bool,
+    /// ドット倍率。レイヤーの (x / n, y / n) のピクセルを n x n に拡大して表示する
+    pub dot_magnification: u32,
+    /// 下のレイヤーでクリッピングする（直下のクリッピングしていないレイヤーの不透明部分だけに描く）
+    pub clip_to_below: bool,
+}
+
+/// 合成するレイヤー1枚分
+#[derive(Clone, Copy, Debug)]
+pub struct StackLayer<'a> {
+    /// RGBAバッファ（キャンバスと同じサイズ）
+    pub buffer: &'a [u8],
+    /// レイヤーマスク（キャンバスと同じサイズ、0-255 の被覆率。MaskFormat::Coverage と同じ）
+    pub mask: Option<&'a [u8]>,
+    pub props: LayerProps,
+}
+
+/// ベースレイヤーの色モード（アプリ側の BaseLayerColorMode と同じ）
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BaseLayerColorMode {
+    Transparent = 0,
+    White = 1,
+    Black = 2,
+    Custom = 3,
+}
+
+/// レイヤーを下から順に合成する（WebGLの LayerMergeRenderer と同じ結果になるCPU版）
+/// layers: 同じサイズのRGBAバッファを下から順に layer_count 枚連結したもの
+/// modes: レイヤーごとのブレンドモード（LayerBlendMode の値）
+/// opacities: レイヤーごとの不透明度 (0.0-1.0)
+/// enabled: レイヤーごとの表示フラグ（0 なら無視する）
+/// clip_to_below: レイヤーごとのクリッピングフラグ（0 以外なら下のレイヤーでクリッピング）
+/// has_mask: レイヤーごとのマスク有無（0 以外ならマスクあり）
+/// masks: マスクありのレイヤーのマスク（width * height、0-255 の被覆率）を下から順に連結したもの
+///
+/// マスクとクリッピングはアルファだけに掛かる（不透明度と違い RGB は変えない）。
+/// ベースレイヤーなしで合成し、最下層の有効なレイヤーがそのまま出発点になる。
+/// 各モードはシェーダーの同名の関数と同じ f32 の式で計算するので、GPUとの差は丸めによる各チャンネル ±1 以内。
+/// modes の値は blendModeIds と同じで、シェーダーの u_blendModes と同じ値を渡せばよい
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn composite_layers(
+    layers: &[u8],
+    width: u32,
+    height: u32,
+    layer_count: u32,
+    modes: &[u8],
+    opacities: &[f32],
+    enabled: &[u8],
+    clip_to_below: &[u8],
+    has_mask: &[u8],
+    masks: &[u8],
+) -> Result<Vec<u8>, JsError> {
+    let layer_len = check_layers_len("layers", layers, width, height, layer_count)?;
+    let props = layer_props_from_slices(layer_count, modes, opacities, enabled, clip_to_below)?;
+    let stack = build_stack(layers, width, height, props, has_mask, masks)?;
+
+    let mut out = vec![0u8; layer_len];
+    composite_stack(&mut out, width, &stack, None, false);
+    Ok(out)
+}
+
+/// ベースレイヤーの色 (0.0-1.0)。getBaseLayerColor と同じ
+/// color: Custom のときの色 [r, g, b, a]。未設定なら透明
+/// linear_light: true なら RGB を linear light に変換して返す
+pub(crate) fn base_layer_color(
+    mode: BaseLayerColorMode,
+    color: Option<&[u8]>,
+    linear_light: bool,
+) -> Result<[f32; 4], JsError> {
+    let color = match mode {
+        BaseLayerColorMode::Transparent => [0.0; 4],
+        BaseLayerColorMode::White => [1.0, 1.0, 1.0, 1.0],
+        BaseLayerColorMode::Black => [0.0, 0.0, 0.0, 1.0],
+        BaseLayerColorMode::Custom => match color {
+            Some([r, g, b, a]) => [*r, *g, *b, *a].map(|c| c as f32 / 255.0),
+            Some(other) => {
+                return Err(JsError::new(&format!(
+                    "base_color: length {} must be 4 (RGBA)",
+                    other.len()
+                )));
+            }
+            None => [0.0; 4],
+        },
+    };
+    Ok(if linear_light {
+        [
+            srgb_to_linear(color[0]),
+            srgb_to_linear(color[1]),
+            srgb_to_linear(color[2]),
+            color[3],
+        ]
+    } else {
+        color
+    })
+}
+
+/// JS から受け取ったレイヤー属性の配列を検証して LayerProps にまとめる
+pub(crate) fn layer_props_from_slices(
+    layer_count: u32,
+    modes: &[u8],
+    opacities: &[f32],
+    enabled: &[u8],
+    clip_to_below: &[u8],
+) -> Result<Vec<LayerProps>, JsError> {
+    let count = layer_count as usize;
+    for (name, len) in [
+        ("modes", modes.len()),
+        ("opacities", opacities.len()),
+        ("enabled", enabled.len()),
+        ("clip_to_below", clip_to_below.len()),
+    ] {
+        if len != count {
+            return Err(JsError::new(&format!(
+                "{name}: length {len} does not match layer_count {count}"
+            )));
+        }
+    }
+
+    (0..count)
+        .map(|i| {
+            let mode = LayerBlendMode::from_id(modes[i]).ok_or_else(|| {
+                JsError::new(&format!("modes[{i}]: unknown blend mode {}", modes[i]))
+            })?;
+            let opacity = opacities[i];
+            if !opacity.is_finite() {
+                return Err(JsError::new(&format!(
+                    "opacities[{i}] must be a finite number"
+                )));
+            }
+            Ok(LayerProps {
+                mode,
+                opacity: opacity.clamp(0.0, 1.0),
+                enabled: enabled[i] != 0,
+                dot_magnification: 1,
+                clip_to_below: clip_to_below[i] != 0,
+            })
+        })
+        .collect()
+}
+
+/// 連結されたレイヤーとマスクを StackLayer の列に分ける
+pub(crate) fn build_stack<'a>(
+    layers: &'a [u8],
+    width: u32,
+    height: u32,
+    props: Vec<LayerProps>,
+    has_mask: &[u8],
+    masks: &'a [u8],
+) -> Result<Vec<StackLayer<'a>>, JsError> {
+    let pixels = pixel_count(width, height)?;
+    if has_mask.len() != props.len() {
+        return Err(JsError::new(&format!(
+            "has_mask: length {} does not match layer_count {}",
+            has_mask.len(),
+            props.len()
+        )));
+    }
+    let mask_count = has_mask.iter().filter(|&&m| m != 0).count();
+    if masks.len() != mask_count.saturating_mul(pixels) {
+        return Err(JsError::new(&format!(
+            "masks: length {} does not match {mask_count} masks of {width}x{height}",
+            masks.len()
+        )));
+    }
+
+    let mut mask_chunks = masks.chunks_exact(pixels.max(1));
+    Ok(layers
+        .chunks_exact((pixels * 4).max(1))
+        .zip(props)
+        .zip(has_mask)
+        .map(|((buffer, props), &m)| StackLayer {
+            buffer,
+            mask: if m != 0 { mask_chunks.next() } else { None },
+            props,
+        })
+        .collect())
+}
+
+/// レイヤーの列を out に合成する（Rust側から使うためのAPI）
+/// stack: 下から順のレイヤー。バッファはすべて out と同じ長さ（幅 width）であること
+/// base: ベースレイヤーの色 (0.0-1.0)。None なら最下層の有効なレイヤーから始める
+/// linear_light: true なら linear light で合成する（base も linear light で渡すこと）。
+///               色は RGB にアルファを掛けていない値のまま扱い、blend_pixel_straight で重ねる
+///
+/// クリッピングするレイヤーのアルファには、直下のクリッピングしていないレイヤー（クリッピングの土台）の
+/// アルファ（不透明度・マスク込み）を掛ける。土台が非表示ならクリッピングするレイヤーも表示しない
+pub fn composite_stack(
+    out: &mut [u8],
+    width: u32,
+    stack: &[StackLayer],
+    base: Option<[f32; 4]>,
+    linear_light: bool,
+) {
+    let w = (width as usize).max(1);
+
+    for (i, px) in out.chunks_exact_mut(4).enumerate() {
+        let mut dst = base;
+        // 現在のクリッピングの土台のアルファ（土台がまだなければ None）
+        let mut clip_base: Option<f32> = None;
+
+        for layer in stack {
+            blend_layer_pixel(layer, w, i, &mut dst, &mut clip_base, linear_light);
+        }
+
+        store_pixel(px, dst.unwrap_or([0.0; 4]), linear_light);
+    }
+}
+
+/// 1枚のレイヤーの i 番目のピクセルを dst に重ねる
+/// clip_base: 現在のクリッピングの土台のアルファ。このレイヤーが土台になるなら更新する
+pub(crate) fn blend_layer_pixel(
+    layer: &StackLayer,
+    width: usize,
+    i: usize,
+    dst: &mut Option<[f32; 4]>,
+    clip_base: &mut Option<f32>,
+    linear_light: bool,
+) {
+    let props = &layer.props;
+    let clipped = props.clip_to_below && clip_base.is_some();
+
+    if !props.enabled {
+        if !clipped {
+            *clip_base = Some(0.0);
+        }
+        return;
+    }
+
+    let mut src = load_pixel(
+        layer.buffer,
+        width,
+        i % width,
+        i / width,
+        props,
+        linear_light,
+    );
+    if let Some(mask) = layer.mask {
+        src[3] *= mask[i] as f32 / 255.0;
+    }
+    match *clip_base {
+        Some(alpha) if clipped => src[3] *= alpha,
+        _ => *clip_base = Some(src[3]),
+    }
+
+    *dst = Some(match *dst {
+        Some(d) => blend_src(src, d, props.mode, linear_light),
+        None => src,
+    });
+}
+
+/// src を dst の上に重ねる。linear_light なら blend_pixel_straight、そうでなければシェーダーと同じ blend_pixel
+#[inline]
+pub(crate) fn blend_src(
+    src: [f32; 4],
+    dst: [f32; 4],
+    mode: LayerBlendMode,
+    linear_light: bool,
+) -> [f32; 4] {
+    if linear_light {
+        blend_pixel_straight(src, dst, mode)
+    } else {
+        blend_pixel(src, dst, mode)
+    }
+}
+
+/// 色に不透明度を掛ける。シェーダーと同じく全チャンネルに掛けるが、linear_light ならアルファだけに掛ける
+#[inline]
+pub(crate) fn apply_opacity(color: [f32; 4], opacity: f32, linear_light: bool) -> [f32; 4] {
+    if linear_light {
+        [color[0], color[1], color[2], color[3] * opacity]
+    } else {
+        color.map(|v| v * opacity)
+    }
+}
+
+// テクスチャのサンプリングと同じく 0-255 を 0.0-1.0 にし、apply_opacity で不透明度を掛ける
+// ドット倍率があるレイヤーは最近傍で拡大して読む
+#[inline]
+fn load_pixel(
+    buffer: &[u8],
+    width: usize,
+    x: usize,
+    y: usize,
+    props: &LayerProps,
+    linear_light: bool,
+) -> [f32; 4] {
+    let n = props.dot_magnification.max(1) as usize;
+    let p = ((y / n) * width + x / n) * 4;
+    let channel = |v: u8| {
+        if linear_light {
+            srgb_byte_to_linear(v)
+        } else {
+            v as f32 / 255.0
+        }
+    };
+    let color = [
+        channel(buffer[p]),
+        channel(buffer[p + 1]),
+        channel(buffer[p + 2]),
+        buffer[p + 3] as f32 / 255.0,
+    ];
+    apply_opacity(color, props.opacity, linear_light)
+}
+
+// フレームバッファへの書き込みと同じく 0.0-1.0 にクランプして 8bit に丸める
+#[inline]
+pub(crate) fn store_pixel(px: &mut [u8], color: [f32; 4], linear_light: bool) {
+    for c in 0..3 {
+        px[c] = if linear_light {
+            linear_to_srgb_byte(color[c])
+        } else {
+            (color[c].clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+    }
+    px[3] = (color[3].clamp(0.0, 1.0) * 255.0).round() as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opacity_is_applied_to_rgb_like_the_shader() {
+        // 下: 不透明 (64, 128, 192)、上: 不透明度 0.5 の (200, 100, 40)
+        // シェーダーは RGB にも不透明度を掛けてから混ぜるので、上の色は 1/4 しか効かない
+        let layers = [64, 128, 192, 255, 200, 100, 40, 255];
+        let out = composite_layers(
+            &layers,
+            1,
+            1,
+            2,
+            &[0, 0],
+            &[1.0, 0.5],
+            &[1, 1],
+            &[0, 0],
+            &[0, 0],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(out, [82, 89, 106, 255]);
+    }
+
+    #[test]
+    fn modes_use_blend_mode_ids() {
+        // blendModeIds の 2 は screen
+        let layers = [64, 128, 192, 255, 200, 100, 50, 255];
+        let out = composite_layers(
+            &layers,
+            1,
+            1,
+            2,
+            &[0, 2],
+            &[1.0, 1.0],
+            &[1, 1],
+            &[0, 0],
+            &[0, 0],
+            &[],
+        )
+        .unwrap();
+        assert_eq!(out, [214, 178, 204, 255]);
+    }
+}
@@ -0,0 +1,6 @@
+// ブレンドモードの計算式
+pub mod blend;
+// レイヤースタックの合成
+pub mod layers;
+// レイヤーグループを含むツリーの合成
+pub mod tree;
@@ -0,0 +1,415 @@
+use wasm_bindgen::prelude::*;
+
+use crate::compositor::blend::LayerBlendMode;
+use crate::compositor::layers::{
+    BaseLayerColorMode, LayerProps, StackLayer, apply_opacity, base_layer_color, blend_layer_pixel,
+    blend_src, store_pixel,
+};
+use crate::utils::validate::{check_finite, check_mask_len, check_rgba_len, pixel_count};
+
+/// レイヤーツリー全体を1枚の画像に統合する（エクスポート・サムネイル用）
+///
+/// 下から順に push_layer でレイヤーを積み、begin_group / end_group でグループを入れ子にする。
+/// 組み立て終わったら flatten で1枚の画像に統合する。
+/// WebGLRenderer の描画（ベースレイヤーあり）と同じ結果を readPixels なしで得られる。
+/// 出力は上下反転していないので flip_pixels_vertically は不要
+///
+/// レイヤーは push_layer の時点で合成し、バッファは保持しない。
+/// 保持するのは合成途中の結果（f32 の RGBA とクリッピングの土台のアルファで 1ピクセル 20 バイト）だけで、
+/// 開いているグループの入れ子1段ごとに1枚ずつ増える。メモリはレイヤー数ではなく入れ子の深さに比例する
+/// （4096x4096 なら1段あたり約 335MB）
+#[wasm_bindgen]
+pub struct LayerTree {
+    width: u32,
+    height: u32,
+    // levels[0] がルート、末尾が最も内側の開いているグループ
+    levels: Vec<Level>,
+    linear_light: bool,
+}
+
+// ルートまたは開いているグループの合成途中の結果
+//
+// dst とクリッピングの土台があるかどうかは全ピクセルで同じなので、ピクセルごとの Option ではなく
+// 段ごとの Option で持つ
+struct Level {
+    kind: LevelKind,
+    // 合成結果。まだ有効なレイヤーがなければ None
+    dst: Option<Vec<[f32; 4]>>,
+    // 現在のクリッピングの土台のアルファ。土台がまだなければ None
+    clip_base: Option<Vec<f32>>,
+}
+
+enum LevelKind {
+    Root,
+    // 子を透明の上で合成してから mode と opacity で親に重ねる
+    Isolated { mode: LayerBlendMode, opacity: f32 },
+    // 子を親の結果に直接重ね、opacity で元の結果と補間する
+    PassThrough { opacity: f32 },
+    // 非表示のグループ（またはその中のグループ）。子は合成しない
+    Disabled,
+}
+
+#[wasm_bindgen]
+impl LayerTree {
+    /// width, height: キャンバスのサイズ
+    /// base_mode: ベースレイヤーの色モード
+    /// base_color: Custom のときの色 [r, g, b, a]。省略時は透明
+    /// linear_light: true なら色を linear light に変換し、RGB に不透明度を掛けない source-over で合成する
+    ///               （境界が暗く濁らない）。false ならシェーダーと同じく sRGB の値のまま合成する
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        width: u32,
+        height: u32,
+        base_mode: BaseLayerColorMode,
+        base_color: Option<Vec<u8>>,
+        linear_light: bool,
+    ) -> Result<LayerTree, JsError> {
+        let pixels = pixel_count(width, height)?;
+        let base = base_layer_color(base_mode, base_color.as_deref(), linear_light)?;
+        Ok(LayerTree {
+            width,
+            height,
+            levels: vec![Level {
+                kind: LevelKind::Root,
+                dst: Some(vec![base; pixels]),
+                clip_base: None,
+            }],
+            linear_light,
+        })
+    }
+
+    /// 現在のグループ（グループ外ならルート）の一番上にレイヤーを重ねる
+    /// buffer: キャンバスと同じサイズのRGBAバッファ（呼び出し後は保持しない）
+    /// mode, opacity (0.0-1.0), enabled, clip_to_below: composite_layers のレイヤーごとの値と同じ
+    /// dot_magnification: ドット倍率（1 以上）。マスクには掛からない
+    /// mask: レイヤーマスク（キャンバスと同じサイズ、0-255 の被覆率）
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_layer(
+        &mut self,
+        buffer: &[u8],
+        mode: LayerBlendMode,
+        opacity: f32,
+        enabled: bool,
+        clip_to_below: bool,
+        dot_magnification: u32,
+        mask: Option<Vec<u8>>,
+    ) -> Result<(), JsError> {
+        check_rgba_len("buffer", buffer, self.width, self.height)?;
+        check_finite("opacity", opacity)?;
+        if dot_magnification == 0 {
+            return Err(JsError::new("dot_magnification must be at least 1"));
+        }
+        if let Some(mask) = &mask {
+            check_mask_len("mask", mask, self.width, self.height)?;
+        }
+
+        let layer = StackLayer {
+            buffer,
+            mask: mask.as_deref(),
+            props: LayerProps {
+                mode,
+                opacity: opacity.clamp(0.0, 1.0),
+                enabled,
+                dot_magnification,
+                clip_to_below,
+            },
+        };
+        let w = (self.width as usize).max(1);
+        let linear_light = self.linear_light;
+        let level = self.current_level();
+        if matches!(level.kind, LevelKind::Disabled) {
+            return Ok(());
+        }
+
+        let pixels = buffer.len() / 4;
+        let had_dst = level.dst.is_some();
+        let had_clip = level.clip_base.is_some();
+        let mut dst = level.dst.take().unwrap_or_else(|| vec![[0.0; 4]; pixels]);
+        let mut clip_base = level.clip_base.take().unwrap_or_else(|| vec![0.0; pixels]);
+        let mut has_dst = had_dst;
+        let mut has_clip = had_clip;
+
+        for i in 0..pixels {
+            let mut d = had_dst.then(|| dst[i]);
+            let mut c = had_clip.then(|| clip_base[i]);
+            blend_layer_pixel(&layer, w, i, &mut d, &mut c, linear_light);
+            if let Some(d) = d {
+                dst[i] = d;
+                has_dst = true;
+            }
+            if let Some(c) = c {
+                clip_base[i] = c;
+                has_clip = true;
+            }
+        }
+
+        level.dst = has_dst.then_some(dst);
+        level.clip_base = has_clip.then_some(clip_base);
+        Ok(())
+    }
+
+    /// グループを開始する。end_group までに積んだレイヤーがこのグループの子になる
+    /// isolated: true なら子を透明の上で先に合成してから mode と opacity で重ねる。
+    ///           false（パススルー）なら子を下のレイヤーに直接重ね、mode は使わず opacity で元の結果と補間する
+    pub fn begin_group(
+        &mut self,
+        mode: LayerBlendMode,
+        opacity: f32,
+        enabled: bool,
+        isolated: bool,
+    ) -> Result<(), JsError> {
+        check_finite("opacity", opacity)?;
+        let opacity = opacity.clamp(0.0, 1.0);
+
+        let parent = self.current_level();
+        let level = if !enabled || matches!(parent.kind, LevelKind::Disabled) {
+            Level {
+                kind: LevelKind::Disabled,
+                dst: None,
+                clip_base: None,
+            }
+        } else if isolated {
+            Level {
+                kind: LevelKind::Isolated { mode, opacity },
+                dst: None,
+                clip_base: None,
+            }
+        } else {
+            Level {
+                kind: LevelKind::PassThrough { opacity },
+                dst: parent.dst.clone(),
+                clip_base: None,
+            }
+        };
+        self.levels.push(level);
+        Ok(())
+    }
+
+    /// 最も内側のグループを閉じ、その結果を親に重ねる
+    ///
+    /// 分離グループは子の合成結果を1枚のレイヤーとして扱い、後続のクリッピングの土台にもなる。
+    /// パススルーグループは土台にならないので、直後のクリッピングするレイヤーは通常のレイヤーとして描く
+    pub fn end_group(&mut self) -> Result<(), JsError> {
+        if self.levels.len() < 2 {
+            return Err(JsError::new(
+                "end_group called without a matching begin_group",
+            ));
+        }
+        let group = self.levels.pop().unwrap();
+        let pixels = pixel_count(self.width, self.height)?;
+        let linear_light = self.linear_light;
+        let parent = self.current_level();
+
+        match group.kind {
+            LevelKind::Root => unreachable!("the root level is never popped"),
+            LevelKind::Disabled => {
+                let mut clip_base = parent.clip_base.take().unwrap_or_else(|| vec![0.0; pixels]);
+                clip_base.fill(0.0);
+                parent.clip_base = Some(clip_base);
+            }
+            LevelKind::Isolated { mode, opacity } => {
+                let mut clip_base = parent.clip_base.take().unwrap_or_else(|| vec![0.0; pixels]);
+                let Some(mut content) = group.dst else {
+                    clip_base.fill(0.0);
+                    parent.clip_base = Some(clip_base);
+                    return Ok(());
+                };
+                for (i, c) in content.iter_mut().enumerate() {
+                    // 不透明度はレイヤーと同じ掛け方をする
+                    let src = apply_opacity(*c, opacity, linear_light);
+                    clip_base[i] = src[3];
+                    *c = match &parent.dst {
+                        Some(dst) => blend_src(src, dst[i], mode, linear_light),
+                        None => src,
+                    };
+                }
+                parent.clip_base = Some(clip_base);
+                parent.dst = Some(content);
+            }
+            LevelKind::PassThrough { opacity } => {
+                if let Some(after) = group.dst {
+                    let before = parent.dst.take();
+                    let dst = after
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &after)| {
+                            let before = before.as_ref().map_or([0.0; 4], |b| b[i]);
+                            mix_pass_through(before, after, opacity, linear_light)
+                        })
+                        .collect();
+                    parent.dst = Some(dst);
+                }
+                parent.clip_base = None;
+            }
+        }
+        Ok(())
+    }
+
+    /// ここまでに積んだレイヤーを1枚のRGBA画像に統合する
+    pub fn flatten(&self) -> Result<Vec<u8>, JsError> {
+        if self.levels.len() > 1 {
+            return Err(JsError::new(&format!(
+                "{} group(s) are not closed with end_group",
+                self.levels.len() - 1
+            )));
+        }
+
+        let mut out = vec![0u8; pixel_count(self.width, self.height)? * 4];
+        if let Some(dst) = &self.levels[0].dst {
+            for (px, &color) in out.chunks_exact_mut(4).zip(dst) {
+                store_pixel(px, color, self.linear_light);
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl LayerTree {
+    fn current_level(&mut self) -> &mut Level {
+        self.levels
+            .last_mut()
+            .expect("the root level is always present")
+    }
+}
+
+// パススルーグループの合成前 (before) と合成後 (after) を opacity で補間する
+#[inline]
+fn mix_pass_through(before: [f32; 4], after: [f32; 4], t: f32, linear_light: bool) -> [f32; 4] {
+    if !linear_light {
+        return [0, 1, 2, 3].map(|c| before[c] + (after[c] - before[c]) * t);
+    }
+
+    // RGB にアルファを掛けていない値なので、アルファで重み付けして補間する
+    let a = before[3] + (after[3] - before[3]) * t;
+    let mut out = [0.0; 4];
+    if a > 0.0 {
+        for c in 0..3 {
+            let premul = before[c] * before[3] + (after[c] * after[3] - before[c] * before[3]) * t;
+            out[c] = premul / a;
+        }
+        out[3] = a;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compositor::layers::composite_stack;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const HALF_BLUE: [u8; 4] = [0, 0, 255, 128];
+
+    fn tree() -> LayerTree {
+        LayerTree::new(1, 1, BaseLayerColorMode::White, None, false).unwrap()
+    }
+
+    fn push(tree: &mut LayerTree, px: [u8; 4], mode: LayerBlendMode, clip: bool) {
+        tree.push_layer(&px, mode, 1.0, true, clip, 1, None)
+            .unwrap();
+    }
+
+    #[test]
+    fn layers_match_composite_stack() {
+        let mut t = tree();
+        push(&mut t, RED, LayerBlendMode::Normal, false);
+        push(&mut t, HALF_BLUE, LayerBlendMode::Multiply, false);
+
+        let props = |mode| LayerProps {
+            mode,
+            opacity: 1.0,
+            enabled: true,
+            dot_magnification: 1,
+            clip_to_below: false,
+        };
+        let stack = [
+            StackLayer {
+                buffer: &RED,
+                mask: None,
+                props: props(LayerBlendMode::Normal),
+            },
+            StackLayer {
+                buffer: &HALF_BLUE,
+                mask: None,
+                props: props(LayerBlendMode::Multiply),
+            },
+        ];
+        let mut expected = [0u8; 4];
+        composite_stack(&mut expected, 1, &stack, Some([1.0; 4]), false);
+
+        assert_eq!(t.flatten().unwrap(), expected);
+    }
+
+    #[test]
+    fn isolated_group_blends_its_content_once() {
+        // 分離グループ内で red に multiply しても透明の上なので red のまま、それを白に multiply で重ねる
+        let mut t = tree();
+        t.begin_group(LayerBlendMode::Multiply, 1.0, true, true)
+            .unwrap();
+        push(&mut t, RED, LayerBlendMode::Multiply, false);
+        t.end_group().unwrap();
+        assert_eq!(t.flatten().unwrap(), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn pass_through_group_mixes_with_opacity() {
+        let mut t = tree();
+        t.begin_group(LayerBlendMode::Normal, 0.5, true, false)
+            .unwrap();
+        push(&mut t, RED, LayerBlendMode::Normal, false);
+        t.end_group().unwrap();
+        assert_eq!(t.flatten().unwrap(), [255, 128, 128, 255]);
+    }
+
+    #[test]
+    fn disabled_group_is_skipped_and_hides_clipped_layers() {
+        let mut t = tree();
+        t.begin_group(LayerBlendMode::Normal, 1.0, false, true)
+            .unwrap();
+        push(&mut t, RED, LayerBlendMode::Normal, false);
+        t.begin_group(LayerBlendMode::Normal, 1.0, true, true)
+            .unwrap();
+        push(&mut t, RED, LayerBlendMode::Normal, false);
+        t.end_group().unwrap();
+        t.end_group().unwrap();
+        push(&mut t, RED, LayerBlendMode::Normal, true);
+        assert_eq!(t.flatten().unwrap(), [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn linear_light_half_opacity_over_white() {
+        let mut t = LayerTree::new(1, 1, BaseLayerColorMode::White, None, true).unwrap();
+        t.push_layer(&RED, LayerBlendMode::Normal, 0.5, true, false, 1, None)
+            .unwrap();
+        assert_eq!(t.flatten().unwrap(), [255, 188, 188, 255]);
+    }
+
+    #[test]
+    fn linear_light_half_alpha_over_transparent() {
+        let mut t = LayerTree::new(1, 1, BaseLayerColorMode::Transparent, None, true).unwrap();
+        push(&mut t, [255, 0, 0, 128], LayerBlendMode::Normal, false);
+        assert_eq!(t.flatten().unwrap(), [255, 0, 0, 128]);
+    }
+
+    #[test]
+    fn dot_magnification_repeats_pixels() {
+        let mut t = LayerTree::new(2, 2, BaseLayerColorMode::Transparent, None, false).unwrap();
+        let mut buffer = [0u8; 16];
+        buffer[..4].copy_from_slice(&RED);
+        t.push_layer(&buffer, LayerBlendMode::Normal, 1.0, true, false, 2, None)
+            .unwrap();
+        assert_eq!(t.flatten().unwrap(), RED.repeat(4));
+    }
+
+    #[test]
+    fn isolated_group_is_a_clipping_base() {
+        let mut t = LayerTree::new(1, 1, BaseLayerColorMode::Transparent, None, false).unwrap();
+        t.begin_group(LayerBlendMode::Normal, 1.0, true, true)
+            .unwrap();
+        push(&mut t, HALF_BLUE, LayerBlendMode::Normal, false);
+        t.end_group().unwrap();
+        push(&mut t, RED, LayerBlendMode::Normal, true);
+        // 赤は青のアルファ (128/255) で切り抜かれる
+        assert_eq!(t.flatten().unwrap(), [128, 0, 64, 192]);
+    }
+}
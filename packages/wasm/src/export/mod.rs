@@ -0,0 +1,2 @@
+// SVGエクスポート（色ごとの輪郭・矩形）
+pub mod svg;
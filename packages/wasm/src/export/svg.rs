@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use wasm_bindgen::prelude::*;
+
+use crate::buffer::rects::decompose_mask_rects;
+use crate::selection::outline::{trace_mask_outline, write_outline_loops};
+use crate::utils::validate::{check_finite, check_rgba_len};
+
+/// RGBAピクセルバッファを色ごとに輪郭抽出したSVGドキュメントを生成
+/// 完全に同じRGBAを持つピクセルを1色としてまとめ、色ごとに <path> を1つ出力する
+/// scale: width/height 属性に掛ける倍率（viewBox は元のサイズのまま）
+/// crisp_edges: true なら shape-rendering="crispEdges" を付ける
+#[wasm_bindgen]
+pub fn export_svg_traced(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    scale: f32,
+    crisp_edges: bool,
+) -> Result<String, JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    check_finite("scale", scale)?;
+    if scale <= 0.0 {
+        return Err(JsError::new("scale must be greater than 0"));
+    }
+
+    let mut body = String::new();
+
+    for_each_color_region(buffer, width as usize, height as usize, |region| {
+        let loops = trace_mask_outline(region.mask, region.width as u32, region.height as u32);
+
+        let mut path = String::new();
+        write_outline_loops(&mut path, &loops, region.x as f32, region.y as f32);
+
+        body.push_str("  <path");
+        write_fill_attributes(&mut body, region.color);
+        let _ = writeln!(body, " d=\"{}\" />", path);
+    });
+
+    Ok(svg_document(width, height, scale, crisp_edges, &body))
+}
+
+/// RGBAピクセルバッファを色ごとに矩形へ分解したSVGドキュメントを生成（ピクセルアート向け）
+/// 座標はすべて整数で、常に shape-rendering="crispEdges" を付けるので矩形の継ぎ目に隙間が出ない
+/// scale: 整数倍率（座標そのものに掛けるので viewBox も拡大後のサイズになる）
+/// as_path: true なら色ごとに1つの <path> にまとめ、false なら <g> 内に <rect> を並べる
+#[wasm_bindgen]
+pub fn export_svg_rects(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    scale: u32,
+    as_path: bool,
+) -> Result<String, JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    if scale == 0 {
+        return Err(JsError::new("scale must be at least 1"));
+    }
+
+    let s = scale;
+    let (Some(scaled_width), Some(scaled_height)) = (width.checked_mul(s), height.checked_mul(s))
+    else {
+        return Err(JsError::new("scaled image size is too large"));
+    };
+    let mut body = String::new();
+
+    for_each_color_region(buffer, width as usize, height as usize, |region| {
+        let rects = decompose_mask_rects(region.mask, region.width as u32, region.height as u32);
+        let (ox, oy) = (region.x as u32, region.y as u32);
+
+        if as_path {
+            let mut path = String::new();
+            for r in &rects {
+                if !path.is_empty() {
+                    path.push(' ');
+                }
+                let _ = write!(
+                    path,
+                    "M{} {}h{}v{}h-{}z",
+                    (ox + r.x) * s,
+                    (oy + r.y) * s,
+                    r.width * s,
+                    r.height * s,
+                    r.width * s
+                );
+            }
+            body.push_str("  <path");
+            write_fill_attributes(&mut body, region.color);
+            let _ = writeln!(body, " d=\"{}\" />", path);
+        } else {
+            body.push_str("  <g");
+            write_fill_attributes(&mut body, region.color);
+            body.push_str(">\n");
+            for r in &rects {
+                let _ = writeln!(
+                    body,
+                    "    <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" />",
+                    (ox + r.x) * s,
+                    (oy + r.y) * s,
+                    r.width * s,
+                    r.height * s
+                );
+            }
+            body.push_str("  </g>\n");
+        }
+    });
+
+    Ok(svg_document(scaled_width, scaled_height, 1.0, true, &body))
+}
+
+// 同じ色のピクセル集合（バウンディングボックス内のマスクとして保持）
+struct ColorRegion<'a> {
+    color: [u8; 4],
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    mask: &'a [u8],
+}
+
+/// 完全に透明なピクセルを除き、RGBAが一致するピクセルごとに領域をまとめて visit に渡す
+/// 領域は色が最初に現れた順（上から、左から）に並ぶ
+///
+/// マスクは1つの作業領域を色ごとに使い回すので、色数によらずメモリは画像サイズの数倍で済む
+fn for_each_color_region(
+    buffer: &[u8],
+    width: usize,
+    height: usize,
+    mut visit: impl FnMut(ColorRegion<'_>),
+) {
+    const NONE: u32 = u32::MAX;
+
+    let total = width * height;
+    let mut index_of: HashMap<u32, u32> = HashMap::new();
+    let mut colors: Vec<[u8; 4]> = Vec::new();
+    // (min_x, min_y, max_x, max_y)
+    let mut bounds: Vec<(usize, usize, usize, usize)> = Vec::new();
+    let mut pixel_index = vec![NONE; total];
+
+    for (i, slot) in pixel_index.iter_mut().enumerate() {
+        let p = i * 4;
+        if p + 3 >= buffer.len() || buffer[p + 3] == 0 {
+            continue;
+        }
+
+        let color = [buffer[p], buffer[p + 1], buffer[p + 2], buffer[p + 3]];
+        let x = i % width;
+        let y = i / width;
+
+        let ci = *index_of
+            .entry(u32::from_le_bytes(color))
+            .or_insert_with(|| {
+                colors.push(color);
+                bounds.push((x, y, x, y));
+                (colors.len() - 1) as u32
+            });
+        *slot = ci;
+
+        let b = &mut bounds[ci as usize];
+        b.0 = b.0.min(x);
+        b.2 = b.2.max(x);
+        b.3 = y;
+    }
+    drop(index_of);
+
+    let mut mask: Vec<u8> = Vec::new();
+    for (ci, (&color, &(min_x, min_y, max_x, max_y))) in colors.iter().zip(&bounds).enumerate() {
+        let rw = max_x - min_x + 1;
+        let rh = max_y - min_y + 1;
+
+        mask.clear();
+        mask.resize(rw * rh, 0);
+        for (y, row) in mask.chunks_exact_mut(rw).enumerate() {
+            let start = (min_y + y) * width + min_x;
+            for (m, &index) in row.iter_mut().zip(&pixel_index[start..start + rw]) {
+                *m = (index == ci as u32) as u8;
+            }
+        }
+
+        visit(ColorRegion {
+            color,
+            x: min_x,
+            y: min_y,
+            width: rw,
+            height: rh,
+            mask: &mask,
+        });
+    }
+}
+
+/// fill="#rrggbb"（不透明でなければ fill-opacity も）を書き出す
+fn write_fill_attributes(out: &mut String, color: [u8; 4]) {
+    let _ = write!(
+        out,
+        " fill=\"#{:02x}{:02x}{:02x}\"",
+        color[0], color[1], color[2]
+    );
+    if color[3] < 255 {
+        let opacity = (color[3] as f32 / 255.0 * 1000.0).round() / 1000.0;
+        let _ = write!(out, " fill-opacity=\"{}\"", opacity);
+    }
+}
+
+/// SVGドキュメント全体を組み立てる
+fn svg_document(width: u32, height: u32, scale: f32, crisp_edges: bool, body: &str) -> String {
+    let scaled_width = (width as f32 * scale).round() as u32;
+    let scaled_height = (height as f32 * scale).round() as u32;
+    let rendering = if crisp_edges {
+        " shape-rendering=\"crispEdges\""
+    } else {
+        ""
+    };
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <svg width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" xmlns=\"http://www.w3.org/2000/svg\"{}>\n\
+         {}</svg>",
+        scaled_width, scaled_height, width, height, rendering, body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 128];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+    // 3x2: 上の行が赤、左下が半透明の青、残りは透明
+    fn sample() -> Vec<u8> {
+        [RED, RED, RED, BLUE, CLEAR, CLEAR].concat()
+    }
+
+    // <svg ...> と </svg> の間の行
+    fn body_lines(svg: &str) -> Vec<&str> {
+        let lines: Vec<&str> = svg.lines().collect();
+        lines[2..lines.len() - 1].to_vec()
+    }
+
+    #[test]
+    fn traced_export_outlines_each_color() {
+        let svg = export_svg_traced(&sample(), 3, 2, 2.0, true).unwrap();
+        assert!(svg.contains(
+            "<svg width=\"6\" height=\"4\" viewBox=\"0 0 3 2\" xmlns=\"http://www.w3.org/2000/svg\" shape-rendering=\"crispEdges\">"
+        ));
+        assert_eq!(
+            body_lines(&svg),
+            [
+                "  <path fill=\"#ff0000\" d=\"M 0 0 L 3 0 L 3 1 L 0 1 Z\" />",
+                "  <path fill=\"#0000ff\" fill-opacity=\"0.502\" d=\"M 0 1 L 1 1 L 1 2 L 0 2 Z\" />",
+            ]
+        );
+        let smooth = export_svg_traced(&sample(), 3, 2, 1.0, false).unwrap();
+        assert!(!smooth.contains("crispEdges"));
+    }
+
+    #[test]
+    fn rect_export_scales_coordinates() {
+        let svg = export_svg_rects(&sample(), 3, 2, 2, true).unwrap();
+        assert!(svg.contains("viewBox=\"0 0 6 4\""));
+        assert_eq!(
+            body_lines(&svg),
+            [
+                "  <path fill=\"#ff0000\" d=\"M0 0h6v2h-6z\" />",
+                "  <path fill=\"#0000ff\" fill-opacity=\"0.502\" d=\"M0 2h2v2h-2z\" />",
+            ]
+        );
+
+        let svg = export_svg_rects(&sample(), 3, 2, 1, false).unwrap();
+        assert_eq!(
+            body_lines(&svg),
+            [
+                "  <g fill=\"#ff0000\">",
+                "    <rect x=\"0\" y=\"0\" width=\"3\" height=\"1\" />",
+                "  </g>",
+                "  <g fill=\"#0000ff\" fill-opacity=\"0.502\">",
+                "    <rect x=\"0\" y=\"1\" width=\"1\" height=\"1\" />",
+                "  </g>",
+            ]
+        );
+    }
+
+    #[test]
+    fn regions_do_not_pick_up_other_colors_in_their_bounds() {
+        // 赤のバウンディングボックスの中に青がある
+        let buffer = [RED, BLUE, RED, RED, RED, RED].concat();
+        let svg = export_svg_rects(&buffer, 3, 2, 1, true).unwrap();
+        assert_eq!(
+            body_lines(&svg),
+            [
+                "  <path fill=\"#ff0000\" d=\"M0 0h1v2h-1z M2 0h1v2h-1z M1 1h1v1h-1z\" />",
+                "  <path fill=\"#0000ff\" fill-opacity=\"0.502\" d=\"M1 0h1v1h-1z\" />",
+            ]
+        );
+    }
+
+    #[test]
+    fn every_pixel_can_be_its_own_color() {
+        let (width, height) = (16u32, 16u32);
+        let buffer: Vec<u8> = (0..width * height)
+            .flat_map(|i| [i as u8, (i >> 8) as u8, 7, 255])
+            .collect();
+        let svg = export_svg_rects(&buffer, width, height, 1, true).unwrap();
+        let lines = body_lines(&svg);
+        assert_eq!(lines.len(), (width * height) as usize);
+        assert_eq!(lines[0], "  <path fill=\"#000007\" d=\"M0 0h1v1h-1z\" />");
+        assert_eq!(lines[17], "  <path fill=\"#110007\" d=\"M1 1h1v1h-1z\" />");
+        assert_eq!(
+            lines[255],
+            "  <path fill=\"#ff0007\" d=\"M15 15h1v1h-1z\" />"
+        );
+    }
+
+    #[test]
+    fn transparent_image_has_an_empty_body() {
+        let buffer = [CLEAR; 4].concat();
+        assert!(body_lines(&export_svg_traced(&buffer, 2, 2, 1.0, true).unwrap()).is_empty());
+        assert!(body_lines(&export_svg_rects(&buffer, 2, 2, 1, false).unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,568 @@
+use wasm_bindgen::prelude::*;
+
+use crate::utils::color::{linear_to_srgb_byte, srgb_byte_to_linear};
+use crate::utils::mask::MaskFormat;
+use crate::utils::validate::{check_finite, check_mask_len, check_rgba_len};
+
+/// 画像の外側をどう扱うか
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlurEdgeMode {
+    /// 端のピクセルを延長する
+    Clamp = 0,
+    /// 反対側の端から折り返す（タイル用）
+    Wrap = 1,
+    /// 外側は透明とみなす
+    Transparent = 2,
+}
+
+// この半径までは正確なガウスカーネルで畳み込み、それより大きければボックスぼかし3回で近似する
+const EXACT_KERNEL_MAX_RADIUS: u32 = 4;
+
+/// ガウスぼかし（その場で書き換える）
+/// radius: カーネルの半径（ピクセル）。0 なら sigma から決める
+/// sigma: 標準偏差。0 以下なら radius / 3（ボックスで近似するときは sigma だけで決まる）
+/// edge: 画像の外側の扱い
+/// selection_mask: 指定すると被覆率に応じてぼかし結果と元の画像を混ぜる
+/// mask_format: selection_mask の形式
+/// linear_light: true なら linear light でぼかす
+///
+/// 乗算済みアルファで計算するので、透明部分の色（多くは黒）が縁ににじまない。
+/// 半径が大きいときは累積和によるボックスぼかし3回で近似するので、半径に関係なくピクセル数に比例した時間で終わる
+/// （3840x2160 でネイティブ実行 0.5 秒ほど、linear_light なら 1 秒ほど。ドラッグ中のプレビューには縮小画像を使うこと）。
+/// 作業用に横方向の結果を 16bit の RGBA で持つので、buffer の 2 倍（3840x2160 で約 66MB）のメモリを確保する
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn gaussian_blur(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    radius: u32,
+    sigma: f32,
+    edge: BlurEdgeMode,
+    selection_mask: Option<Vec<u8>>,
+    mask_format: MaskFormat,
+    linear_light: bool,
+) -> Result<(), JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    check_finite("sigma", sigma)?;
+    if let Some(mask) = &selection_mask {
+        check_mask_len("selection_mask", mask, width, height)?;
+    }
+
+    let sigma = if sigma > 0.0 {
+        sigma
+    } else {
+        radius as f32 / 3.0
+    };
+    let radius = if radius > 0 {
+        radius
+    } else {
+        (sigma * 3.0).ceil() as u32
+    };
+    if radius == 0 || sigma <= 0.0 {
+        return Ok(());
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let mask = selection_mask.as_deref().map(|m| (m, mask_format));
+
+    if radius <= EXACT_KERNEL_MAX_RADIUS {
+        let kernel = gaussian_kernel(radius as usize, sigma);
+        let ops = [LineOp::Convolve(&kernel)];
+        separable_pass(buffer, w, h, edge, &ops, mask, linear_light);
+    } else {
+        let ops = gaussian_box_radii(sigma).map(|r| LineOp::Box(r.min(max_box_radius(w, h))));
+        separable_pass(buffer, w, h, edge, &ops, mask, linear_light);
+    }
+    Ok(())
+}
+
+/// ボックスぼかし（その場で書き換える）
+/// radius: 半径（(2 * radius + 1) 四方の平均）
+/// iterations: 繰り返し回数（3 回でガウスぼかしに近くなる、0 なら何もしない）
+/// edge, selection_mask, mask_format, linear_light: gaussian_blur と同じ
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn box_blur(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    radius: u32,
+    iterations: u32,
+    edge: BlurEdgeMode,
+    selection_mask: Option<Vec<u8>>,
+    mask_format: MaskFormat,
+    linear_light: bool,
+) -> Result<(), JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    if let Some(mask) = &selection_mask {
+        check_mask_len("selection_mask", mask, width, height)?;
+    }
+    if radius == 0 || iterations == 0 {
+        return Ok(());
+    }
+
+    let (w, h) = (width as usize, height as usize);
+    let r = (radius as usize).min(max_box_radius(w, h));
+    let ops = vec![LineOp::Box(r); iterations as usize];
+    let mask = selection_mask.as_deref().map(|m| (m, mask_format));
+    separable_pass(buffer, w, h, edge, &ops, mask, linear_light);
+    Ok(())
+}
+
+// ボックスの半径の上限。画像より大きい半径は結果がほとんど変わらず、作業用の列だけが大きくなる
+fn max_box_radius(width: usize, height: usize) -> usize {
+    width.max(height).max(1)
+}
+
+// 半径 radius の正規化済みガウスカーネル（長さ 2 * radius + 1）
+fn gaussian_kernel(radius: usize, sigma: f32) -> Vec<f32> {
+    let denom = 2.0 * sigma * sigma;
+    let mut kernel: Vec<f32> = (0..=2 * radius)
+        .map(|i| {
+            let d = i as f32 - radius as f32;
+            (-d * d / denom).exp()
+        })
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|k| *k /= sum);
+    kernel
+}
+
+// sigma のガウスぼかしを近似する3回分のボックスの半径
+// （幅 wl と wl + 2 の箱を分散の合計が 12 * sigma^2 に近くなるよう組み合わせる）
+fn gaussian_box_radii(sigma: f32) -> [usize; 3] {
+    const N: f32 = 3.0;
+    let ideal = (12.0 * sigma * sigma / N + 1.0).sqrt();
+    let mut wl = ideal.floor() as i32;
+    if wl % 2 == 0 {
+        wl -= 1;
+    }
+    let wl = wl.max(1);
+    let wu = wl + 2;
+    let wlf = wl as f32;
+    let m = ((12.0 * sigma * sigma - N * wlf * wlf - 4.0 * N * wlf - 3.0 * N) / (-4.0 * wlf - 4.0))
+        .round() as i32;
+
+    std::array::from_fn(|i| {
+        let size = if (i as i32) < m { wl } else { wu };
+        ((size - 1) / 2) as usize
+    })
+}
+
+// ---------------------------------------------------------------------------
+// 1次元の処理
+// line は前後に pad 要素ずつ画像外の値を足した列、out は元の長さの出力先。
+// 1要素は stride 個の f32（横方向は1ピクセルの RGBA、縦方向は数十列分の行）
+// ---------------------------------------------------------------------------
+
+fn convolve_line(line: &[f32], out: &mut [f32], kernel: &[f32], stride: usize) {
+    // タップごとに列全体へ積和する（連続したメモリを順に読むのでベクトル化しやすい）
+    out.fill(0.0);
+    for (k, &weight) in kernel.iter().enumerate() {
+        let src = &line[k * stride..k * stride + out.len()];
+        for (o, &v) in out.iter_mut().zip(src) {
+            *o += v * weight;
+        }
+    }
+}
+
+// 累積和による平均。半径に関係なく1要素あたり定数回の加減算で済む
+fn box_sum_line(line: &[f32], out: &mut [f32], radius: usize, stride: usize) {
+    if stride == 4 {
+        box_sum_line_fixed::<4>(line, out, radius);
+        return;
+    }
+
+    let size = 2 * radius + 1;
+    let scale = 1.0 / size as f32;
+
+    // f32 の誤差が溜まらないよう f64 で足し引きする
+    let mut acc = vec![0.0f64; stride];
+    for src in line[..size * stride].chunks_exact(stride) {
+        for (a, &v) in acc.iter_mut().zip(src) {
+            *a += v as f64;
+        }
+    }
+
+    let mut entering = line[size * stride..].chunks_exact(stride);
+    for (o, leaving) in out.chunks_exact_mut(stride).zip(line.chunks_exact(stride)) {
+        for (o, &a) in o.iter_mut().zip(&acc) {
+            *o = a as f32 * scale;
+        }
+        if let Some(src) = entering.next() {
+            for ((a, &v), &l) in acc.iter_mut().zip(src).zip(leaving) {
+                *a += v as f64 - l as f64;
+            }
+        }
+    }
+}
+
+// 要素の長さが固定（横方向の1ピクセル）の box_sum_line
+fn box_sum_line_fixed<const S: usize>(line: &[f32], out: &mut [f32], radius: usize) {
+    let size = 2 * radius + 1;
+    let scale = 1.0 / size as f32;
+
+    let mut acc = [0.0f64; S];
+    for src in line[..size * S].chunks_exact(S) {
+        for c in 0..S {
+            acc[c] += src[c] as f64;
+        }
+    }
+
+    let n = out.len() / S;
+    for i in 0..n {
+        for c in 0..S {
+            out[i * S + c] = acc[c] as f32 * scale;
+        }
+        if i + 1 < n {
+            let entering = &line[(i + size) * S..(i + size + 1) * S];
+            let leaving = &line[i * S..(i + 1) * S];
+            for c in 0..S {
+                acc[c] += entering[c] as f64 - leaving[c] as f64;
+            }
+        }
+    }
+}
+
+/// 1次元の処理
+#[derive(Clone, Copy, Debug)]
+enum LineOp<'a> {
+    /// 長さ 2 * radius + 1 のカーネルによる畳み込み
+    Convolve(&'a [f32]),
+    /// 半径 radius のボックス平均
+    Box(usize),
+}
+
+impl LineOp<'_> {
+    fn radius(&self) -> usize {
+        match self {
+            LineOp::Convolve(kernel) => kernel.len() / 2,
+            LineOp::Box(radius) => *radius,
+        }
+    }
+
+    fn apply(&self, line: &[f32], out: &mut [f32], stride: usize) {
+        match self {
+            LineOp::Convolve(kernel) => convolve_line(line, out, kernel, stride),
+            LineOp::Box(radius) => box_sum_line(line, out, *radius, stride),
+        }
+    }
+}
+
+// 縦方向の処理で一度に扱う列数（この幅の行をまとめて1要素として処理する）
+const COLUMN_STRIP: usize = 16;
+
+// 横方向、縦方向の順に ops を続けてかけ、結果を buffer に書く
+// 1行（縦方向は COLUMN_STRIP 列分）を取り出したまま全部の処理をかけるので、画像全体を読み書きするのは方向ごとに1回で済む。
+// 方向の間の結果は乗算済みアルファの 16bit で持つ（8bit の出力には十分な精度で、f32 の半分のメモリで済む）
+fn separable_pass(
+    buffer: &mut [u8],
+    width: usize,
+    height: usize,
+    edge: BlurEdgeMode,
+    ops: &[LineOp],
+    mask: Option<(&[u8], MaskFormat)>,
+    linear_light: bool,
+) {
+    if width == 0 || height == 0 {
+        return;
+    }
+    let max_pad = ops.iter().map(|op| op.radius()).max().unwrap_or(0);
+
+    // 横方向（1要素 = 1ピクセル）
+    let mut planes = vec![0u16; buffer.len()];
+    let mut row = vec![0.0f32; width * 4];
+    let mut line = vec![0.0f32; (width + 2 * max_pad) * 4];
+    for y in 0..height {
+        let range = y * width * 4..(y + 1) * width * 4;
+        for (out, px) in row
+            .chunks_exact_mut(4)
+            .zip(buffer[range.clone()].chunks_exact(4))
+        {
+            out.copy_from_slice(&premultiply_pixel(px, linear_light));
+        }
+        apply_ops(&mut row, &mut line, width, 4, edge, ops);
+        for (q, &v) in planes[range].iter_mut().zip(&row) {
+            *q = (v.clamp(0.0, 1.0) * 65535.0 + 0.5) as u16;
+        }
+    }
+
+    // 縦方向（1要素 = COLUMN_STRIP 列分の行）
+    let mut lines = vec![0.0f32; (height + 2 * max_pad) * COLUMN_STRIP * 4];
+    let mut strip = vec![0.0f32; height * COLUMN_STRIP * 4];
+    for x0 in (0..width).step_by(COLUMN_STRIP) {
+        let stride = COLUMN_STRIP.min(width - x0) * 4;
+        let strip = &mut strip[..height * stride];
+
+        for y in 0..height {
+            let p = (y * width + x0) * 4;
+            for (v, &q) in strip[y * stride..(y + 1) * stride]
+                .iter_mut()
+                .zip(&planes[p..p + stride])
+            {
+                *v = q as f32 / 65535.0;
+            }
+        }
+        apply_ops(strip, &mut lines, height, stride, edge, ops);
+        for y in 0..height {
+            let p = (y * width + x0) * 4;
+            let blurred = &strip[y * stride..(y + 1) * stride];
+            for (k, (px, value)) in buffer[p..p + stride]
+                .chunks_exact_mut(4)
+                .zip(blurred.chunks_exact(4))
+                .enumerate()
+            {
+                let i = y * width + x0 + k;
+                let coverage = mask.map_or(255, |(m, format)| format.coverage(m[i]));
+                store_premultiplied(px, value, coverage, linear_light);
+            }
+        }
+    }
+}
+
+// n 要素の data に ops を順にかける（line は作業用）
+fn apply_ops(
+    data: &mut [f32],
+    line: &mut [f32],
+    n: usize,
+    stride: usize,
+    edge: BlurEdgeMode,
+    ops: &[LineOp],
+) {
+    for op in ops {
+        let pad = op.radius();
+        let line = &mut line[..(n + 2 * pad) * stride];
+
+        line[pad * stride..(pad + n) * stride].copy_from_slice(data);
+        for k in (0..pad).chain(pad + n..n + 2 * pad) {
+            let dst = &mut line[k * stride..(k + 1) * stride];
+            match edge_index(k as isize - pad as isize, n, edge) {
+                Some(i) => dst.copy_from_slice(&data[i * stride..(i + 1) * stride]),
+                None => dst.fill(0.0),
+            }
+        }
+
+        op.apply(line, data, stride);
+    }
+}
+
+// 長さ n の列の i 番目（範囲外を含む）が参照する位置。Transparent で範囲外なら None
+#[inline]
+fn edge_index(i: isize, n: usize, edge: BlurEdgeMode) -> Option<usize> {
+    let n = n as isize;
+    if (0..n).contains(&i) {
+        return Some(i as usize);
+    }
+    match edge {
+        BlurEdgeMode::Clamp => Some(i.clamp(0, n - 1) as usize),
+        BlurEdgeMode::Wrap => Some(i.rem_euclid(n) as usize),
+        BlurEdgeMode::Transparent => None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// 乗算済みアルファとの変換
+// ---------------------------------------------------------------------------
+
+#[inline]
+fn premultiply_pixel(px: &[u8], linear_light: bool) -> [f32; 4] {
+    let a = px[3] as f32 / 255.0;
+    let channel = |v: u8| {
+        if linear_light {
+            srgb_byte_to_linear(v)
+        } else {
+            v as f32 / 255.0
+        }
+    };
+    [
+        channel(px[0]) * a,
+        channel(px[1]) * a,
+        channel(px[2]) * a,
+        a,
+    ]
+}
+
+// 乗算済みアルファのぼかし結果をストレートアルファに戻して px に書く
+// coverage: 選択範囲の被覆率。0 なら元のまま残し、中間の値なら元のピクセルと混ぜる
+#[inline]
+fn store_premultiplied(px: &mut [u8], blurred: &[f32], coverage: u8, linear_light: bool) {
+    if coverage == 0 {
+        return;
+    }
+
+    let mut value = [blurred[0], blurred[1], blurred[2], blurred[3]];
+    if coverage < 255 {
+        // 乗算済みのまま元の画像と混ぜる
+        let t = coverage as f32 / 255.0;
+        let original = premultiply_pixel(px, linear_light);
+        for c in 0..4 {
+            value[c] = original[c] + (value[c] - original[c]) * t;
+        }
+    }
+
+    let a = value[3].clamp(0.0, 1.0);
+    if a <= 0.0 {
+        px.fill(0);
+        return;
+    }
+    let inv_a = 1.0 / a;
+    for c in 0..3 {
+        let straight = (value[c] * inv_a).clamp(0.0, 1.0);
+        // 非負なので +0.5 の切り捨てで四捨五入になる
+        px[c] = if linear_light {
+            linear_to_srgb_byte(straight)
+        } else {
+            (straight * 255.0 + 0.5) as u8
+        };
+    }
+    px[3] = (a * 255.0 + 0.5) as u8;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EDGES: [BlurEdgeMode; 3] = [
+        BlurEdgeMode::Clamp,
+        BlurEdgeMode::Wrap,
+        BlurEdgeMode::Transparent,
+    ];
+
+    // 1行4ピクセルの不透明な灰色（右端だけ 90）
+    fn gray_row() -> Vec<u8> {
+        [0u8, 0, 0, 90]
+            .iter()
+            .flat_map(|&v| [v, v, v, 255])
+            .collect()
+    }
+
+    fn box_once(buffer: &mut [u8], edge: BlurEdgeMode, mask: Option<Vec<u8>>, format: MaskFormat) {
+        box_blur(buffer, 4, 1, 1, 1, edge, mask, format, false).unwrap();
+    }
+
+    #[test]
+    fn constant_image_is_unchanged() {
+        let original: Vec<u8> = [40u8, 120, 200, 160].repeat(12 * 9);
+        for edge in [BlurEdgeMode::Clamp, BlurEdgeMode::Wrap] {
+            for linear_light in [false, true] {
+                for radius in [2, 20] {
+                    let mut buffer = original.clone();
+                    let format = MaskFormat::Binary;
+                    gaussian_blur(
+                        &mut buffer,
+                        12,
+                        9,
+                        radius,
+                        0.0,
+                        edge,
+                        None,
+                        format,
+                        linear_light,
+                    )
+                    .unwrap();
+                    assert_eq!(buffer, original, "{edge:?} radius {radius}");
+                }
+                let mut buffer = original.clone();
+                box_blur(
+                    &mut buffer,
+                    12,
+                    9,
+                    3,
+                    3,
+                    edge,
+                    None,
+                    MaskFormat::Binary,
+                    linear_light,
+                )
+                .unwrap();
+                assert_eq!(buffer, original, "{edge:?} box");
+            }
+        }
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_bleed_black() {
+        // 左半分が不透明な赤、右半分が透明な黒
+        let (w, h) = (10, 4);
+        let mut buffer: Vec<u8> = (0..w * h)
+            .flat_map(|i| {
+                if i % w < w / 2 {
+                    [255, 0, 0, 255]
+                } else {
+                    [0; 4]
+                }
+            })
+            .collect();
+        for edge in EDGES {
+            let format = MaskFormat::Binary;
+            gaussian_blur(
+                &mut buffer,
+                w as u32,
+                h as u32,
+                6,
+                0.0,
+                edge,
+                None,
+                format,
+                false,
+            )
+            .unwrap();
+            for px in buffer.chunks_exact(4).filter(|px| px[3] > 0) {
+                assert_eq!(&px[..3], &[255, 0, 0], "{edge:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn edge_modes() {
+        let cases = [
+            (
+                BlurEdgeMode::Clamp,
+                [[0, 255], [0, 255], [30, 255], [60, 255]],
+            ),
+            (
+                BlurEdgeMode::Wrap,
+                [[30, 255], [0, 255], [30, 255], [30, 255]],
+            ),
+            // 外側が透明なので、縦方向（高さ 1）でもアルファが 1/3 になる
+            (
+                BlurEdgeMode::Transparent,
+                [[0, 57], [0, 85], [30, 85], [45, 57]],
+            ),
+        ];
+        for (edge, expected) in cases {
+            let mut buffer = gray_row();
+            box_once(&mut buffer, edge, None, MaskFormat::Binary);
+            let expected: Vec<u8> = expected.iter().flat_map(|&[v, a]| [v, v, v, a]).collect();
+            assert_eq!(buffer, expected, "{edge:?}");
+        }
+    }
+
+    #[test]
+    fn selection_mask_limits_the_blur() {
+        let mask = vec![255, 255, 0, 128];
+
+        let mut buffer = gray_row();
+        box_once(
+            &mut buffer,
+            BlurEdgeMode::Clamp,
+            Some(mask.clone()),
+            MaskFormat::Coverage,
+        );
+        // 被覆率 0 は元のまま、128 はぼかし結果 (60) と元 (90) の中間
+        let values: Vec<u8> = buffer.chunks_exact(4).map(|px| px[0]).collect();
+        assert_eq!(values, [0, 0, 0, 75]);
+
+        let mut buffer = gray_row();
+        box_once(
+            &mut buffer,
+            BlurEdgeMode::Clamp,
+            Some(mask),
+            MaskFormat::Binary,
+        );
+        let values: Vec<u8> = buffer.chunks_exact(4).map(|px| px[0]).collect();
+        assert_eq!(values, [0, 0, 0, 60]);
+    }
+}
@@ -0,0 +1,448 @@
+use wasm_bindgen::prelude::*;
+
+use crate::utils::validate::{check_finite, check_rgba_len};
+
+/// ディザリングの方法
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DitherMethod {
+    /// ディザリングなし（最も近い色に置き換えるだけ）
+    None = 0,
+    FloydSteinberg = 1,
+    Atkinson = 2,
+    JarvisJudiceNinke = 3,
+    Sierra = 4,
+    /// 2x2 の組織的ディザリング
+    Bayer2 = 5,
+    /// 4x4 の組織的ディザリング
+    Bayer4 = 6,
+    /// 8x8 の組織的ディザリング
+    Bayer8 = 7,
+}
+
+// 誤差拡散のカーネル（dx, dy, 重み）と重みの分母
+// Atkinson は重みの合計が分母より小さく、誤差の 1/4 を捨てる
+const FLOYD_STEINBERG: (&[(i32, i32, f32)], f32) =
+    (&[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)], 16.0);
+const ATKINSON: (&[(i32, i32, f32)], f32) = (
+    &[
+        (1, 0, 1.0),
+        (2, 0, 1.0),
+        (-1, 1, 1.0),
+        (0, 1, 1.0),
+        (1, 1, 1.0),
+        (0, 2, 1.0),
+    ],
+    8.0,
+);
+const JARVIS_JUDICE_NINKE: (&[(i32, i32, f32)], f32) = (
+    &[
+        (1, 0, 7.0),
+        (2, 0, 5.0),
+        (-2, 1, 3.0),
+        (-1, 1, 5.0),
+        (0, 1, 7.0),
+        (1, 1, 5.0),
+        (2, 1, 3.0),
+        (-2, 2, 1.0),
+        (-1, 2, 3.0),
+        (0, 2, 5.0),
+        (1, 2, 3.0),
+        (2, 2, 1.0),
+    ],
+    48.0,
+);
+const SIERRA: (&[(i32, i32, f32)], f32) = (
+    &[
+        (1, 0, 5.0),
+        (2, 0, 3.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 5.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-1, 2, 2.0),
+        (0, 2, 3.0),
+        (1, 2, 2.0),
+    ],
+    32.0,
+);
+
+/// 画像を任意のパレットの色だけで表す（その場で書き換える）
+/// palette: [r, g, b, r, g, b, ...]（プロジェクトのパレットやスウォッチなど）
+/// method: ディザリングの方法
+/// strength: 誤差拡散では拡散する誤差、組織的ディザリングでは閾値の振れ幅に掛ける係数 (0.0-1.0)
+/// serpentine: true なら誤差拡散で行ごとに走査方向を反転する（模様の偏りが減る）
+///
+/// アルファはそのまま残し、完全に透明なピクセルは変えない（誤差も受け渡さない）
+#[allow(clippy::too_many_arguments)]
+#[wasm_bindgen]
+pub fn dither_to_palette(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    palette: &[u8],
+    method: DitherMethod,
+    strength: f32,
+    serpentine: bool,
+) -> Result<(), JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    check_finite("strength", strength)?;
+    let palette = parse_palette(palette)?;
+    let strength = strength.clamp(0.0, 1.0);
+    let (w, h) = (width as usize, height as usize);
+
+    match method {
+        DitherMethod::None => {
+            for px in buffer.chunks_exact_mut(4) {
+                if px[3] != 0 {
+                    let c = palette
+                        [nearest_color(&palette, [px[0] as f32, px[1] as f32, px[2] as f32])];
+                    px[..3].copy_from_slice(&c);
+                }
+            }
+        }
+        DitherMethod::FloydSteinberg => error_diffusion(
+            buffer,
+            w,
+            h,
+            &palette,
+            FLOYD_STEINBERG,
+            strength,
+            serpentine,
+        ),
+        DitherMethod::Atkinson => {
+            error_diffusion(buffer, w, h, &palette, ATKINSON, strength, serpentine)
+        }
+        DitherMethod::JarvisJudiceNinke => error_diffusion(
+            buffer,
+            w,
+            h,
+            &palette,
+            JARVIS_JUDICE_NINKE,
+            strength,
+            serpentine,
+        ),
+        DitherMethod::Sierra => {
+            error_diffusion(buffer, w, h, &palette, SIERRA, strength, serpentine)
+        }
+        DitherMethod::Bayer2 => ordered(buffer, w, &palette, &bayer_matrix(1), 2, strength),
+        DitherMethod::Bayer4 => ordered(buffer, w, &palette, &bayer_matrix(2), 4, strength),
+        DitherMethod::Bayer8 => ordered(buffer, w, &palette, &bayer_matrix(3), 8, strength),
+    }
+
+    Ok(())
+}
+
+/// [r, g, b, ...] のパレットを検証して色の列にする
+pub(crate) fn parse_palette(palette: &[u8]) -> Result<Vec<[u8; 3]>, JsError> {
+    if palette.is_empty() || !palette.len().is_multiple_of(3) {
+        return Err(JsError::new(&format!(
+            "palette: length {} must be a positive multiple of 3 (RGB)",
+            palette.len()
+        )));
+    }
+    Ok(palette
+        .chunks_exact(3)
+        .map(|c| [c[0], c[1], c[2]])
+        .collect())
+}
+
+/// RGB 空間で最も近いパレットの色の番号
+#[inline]
+pub(crate) fn nearest_color(palette: &[[u8; 3]], color: [f32; 3]) -> usize {
+    let mut best = 0;
+    let mut best_dist = f32::MAX;
+    for (i, p) in palette.iter().enumerate() {
+        let dr = p[0] as f32 - color[0];
+        let dg = p[1] as f32 - color[1];
+        let db = p[2] as f32 - color[2];
+        let dist = dr * dr + dg * dg + db * db;
+        if dist < best_dist {
+            best_dist = dist;
+            best = i;
+        }
+    }
+    best
+}
+
+// 誤差拡散（各ピクセルの量子化誤差をカーネルに従って未処理のピクセルへ配る）
+fn error_diffusion(
+    buffer: &mut [u8],
+    width: usize,
+    height: usize,
+    palette: &[[u8; 3]],
+    (kernel, divisor): (&[(i32, i32, f32)], f32),
+    strength: f32,
+    serpentine: bool,
+) {
+    let rows = kernel
+        .iter()
+        .map(|&(_, dy, _)| dy as usize)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    // 現在の行から rows 行分の誤差（行は y % rows に置く）
+    let mut errors = vec![[0.0f32; 3]; width * rows];
+    let scale = strength / divisor;
+
+    for y in 0..height {
+        let reverse = serpentine && y % 2 == 1;
+        let dir = if reverse { -1 } else { 1 };
+
+        for step in 0..width {
+            let x = if reverse { width - 1 - step } else { step };
+            let slot = (y % rows) * width + x;
+            let err = std::mem::take(&mut errors[slot]);
+
+            let p = (y * width + x) * 4;
+            if buffer[p + 3] == 0 {
+                continue;
+            }
+
+            let wanted = [0, 1, 2].map(|c| (buffer[p + c] as f32 + err[c]).clamp(0.0, 255.0));
+            let chosen = palette[nearest_color(palette, wanted)];
+            buffer[p..p + 3].copy_from_slice(&chosen);
+
+            let diff = [0, 1, 2].map(|c| (wanted[c] - chosen[c] as f32) * scale);
+            for &(dx, dy, weight) in kernel {
+                let nx = x as i64 + (dx * dir) as i64;
+                let ny = y + dy as usize;
+                if nx < 0 || nx >= width as i64 || ny >= height {
+                    continue;
+                }
+                let e = &mut errors[(ny % rows) * width + nx as usize];
+                for c in 0..3 {
+                    e[c] += diff[c] * weight;
+                }
+            }
+        }
+    }
+}
+
+// パレットの色の平均的な間隔（組織的ディザリングの閾値の振れ幅に使う）
+// 各色から最も近い別の色までのチャンネルごとの差の最大値を平均する。
+// 白黒なら 255、各チャンネル等間隔のパレットならその間隔になる
+fn palette_spacing(palette: &[[u8; 3]]) -> f32 {
+    if palette.len() < 2 {
+        return 0.0;
+    }
+    let total: u32 = palette
+        .iter()
+        .enumerate()
+        .map(|(i, a)| {
+            palette
+                .iter()
+                .enumerate()
+                .filter(|&(j, b)| j != i && b != a)
+                .map(|(_, b)| {
+                    (0..3)
+                        .map(|c| a[c].abs_diff(b[c]) as u32)
+                        .max()
+                        .unwrap_or(0)
+                })
+                .min()
+                .unwrap_or(0)
+        })
+        .sum();
+    total as f32 / palette.len() as f32
+}
+
+// n x n（n = 2^level）の Bayer 行列。値は 0..n*n
+fn bayer_matrix(level: u32) -> Vec<u32> {
+    let mut m = vec![0u32];
+    let mut n = 1usize;
+    for _ in 0..level {
+        let mut next = vec![0u32; 4 * n * n];
+        for y in 0..n {
+            for x in 0..n {
+                let v = 4 * m[y * n + x];
+                next[y * 2 * n + x] = v;
+                next[y * 2 * n + x + n] = v + 2;
+                next[(y + n) * 2 * n + x] = v + 3;
+                next[(y + n) * 2 * n + x + n] = v + 1;
+            }
+        }
+        m = next;
+        n *= 2;
+    }
+    m
+}
+
+// 組織的ディザリング（位置ごとの閾値を足してから最も近い色を選ぶ）
+fn ordered(
+    buffer: &mut [u8],
+    width: usize,
+    palette: &[[u8; 3]],
+    matrix: &[u32],
+    n: usize,
+    strength: f32,
+) {
+    let spread = palette_spacing(palette) * strength;
+    let cells = (n * n) as f32;
+
+    for (i, px) in buffer.chunks_exact_mut(4).enumerate() {
+        if px[3] == 0 {
+            continue;
+        }
+        let (x, y) = (i % width, i / width);
+        let threshold = (matrix[(y % n) * n + x % n] as f32 + 0.5) / cells - 0.5;
+        let offset = threshold * spread;
+
+        let wanted = [0, 1, 2].map(|c| px[c] as f32 + offset);
+        let chosen = palette[nearest_color(palette, wanted)];
+        px[..3].copy_from_slice(&chosen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK_WHITE: [u8; 6] = [0, 0, 0, 255, 255, 255];
+
+    fn flat(width: u32, height: u32, value: u8, alpha: u8) -> Vec<u8> {
+        [value, value, value, alpha].repeat((width * height) as usize)
+    }
+
+    // 白くなったピクセルを true にする
+    fn whites(buffer: &[u8]) -> Vec<bool> {
+        buffer.chunks(4).map(|px| px[0] == 255).collect()
+    }
+
+    #[test]
+    fn bayer_matrices_follow_the_recursive_layout() {
+        assert_eq!(bayer_matrix(1), [0, 2, 3, 1]);
+        assert_eq!(
+            bayer_matrix(2),
+            [0, 8, 2, 10, 12, 4, 14, 6, 3, 11, 1, 9, 15, 7, 13, 5]
+        );
+    }
+
+    #[test]
+    fn bayer2_thresholds_mid_grey() {
+        let mut buffer = flat(2, 2, 128, 255);
+        dither_to_palette(
+            &mut buffer,
+            2,
+            2,
+            &BLACK_WHITE,
+            DitherMethod::Bayer2,
+            1.0,
+            false,
+        )
+        .unwrap();
+        assert_eq!(whites(&buffer), [false, true, true, false]);
+
+        let mut buffer = flat(2, 2, 64, 255);
+        dither_to_palette(
+            &mut buffer,
+            2,
+            2,
+            &BLACK_WHITE,
+            DitherMethod::Bayer2,
+            1.0,
+            false,
+        )
+        .unwrap();
+        assert_eq!(whites(&buffer), [false, false, true, false]);
+    }
+
+    #[test]
+    fn bayer4_turns_the_upper_half_of_the_matrix_white() {
+        let mut buffer = flat(4, 4, 128, 255);
+        dither_to_palette(
+            &mut buffer,
+            4,
+            4,
+            &BLACK_WHITE,
+            DitherMethod::Bayer4,
+            1.0,
+            false,
+        )
+        .unwrap();
+        let expected: Vec<bool> = bayer_matrix(2).iter().map(|&v| v >= 8).collect();
+        assert_eq!(whites(&buffer), expected);
+    }
+
+    #[test]
+    fn serpentine_reverses_odd_rows() {
+        // 100 の灰色 2x2 を Floyd-Steinberg で白黒にする
+        let mut forward = flat(2, 2, 100, 255);
+        dither_to_palette(
+            &mut forward,
+            2,
+            2,
+            &BLACK_WHITE,
+            DitherMethod::FloydSteinberg,
+            1.0,
+            false,
+        )
+        .unwrap();
+        assert_eq!(whites(&forward), [false, true, false, false]);
+
+        // 2行目は右から処理され、右端の誤差が左端に届いて白になる
+        let mut serpentine = flat(2, 2, 100, 255);
+        dither_to_palette(
+            &mut serpentine,
+            2,
+            2,
+            &BLACK_WHITE,
+            DitherMethod::FloydSteinberg,
+            1.0,
+            true,
+        )
+        .unwrap();
+        assert_eq!(whites(&serpentine), [false, true, true, false]);
+    }
+
+    #[test]
+    fn every_method_stays_in_the_palette_and_keeps_alpha() {
+        let palette = [0, 0, 0, 255, 0, 0, 40, 200, 255];
+        let (width, height) = (8u32, 6u32);
+        let source: Vec<u8> = (0..width * height)
+            .flat_map(|i| {
+                let v = (i * 37 % 256) as u8;
+                // 透明・半透明・不透明を混ぜる
+                let alpha = [0, 90, 255][(i % 3) as usize];
+                [v, 255 - v, (i * 11 % 256) as u8, alpha]
+            })
+            .collect();
+
+        for method in [
+            DitherMethod::None,
+            DitherMethod::FloydSteinberg,
+            DitherMethod::Atkinson,
+            DitherMethod::JarvisJudiceNinke,
+            DitherMethod::Sierra,
+            DitherMethod::Bayer2,
+            DitherMethod::Bayer4,
+            DitherMethod::Bayer8,
+        ] {
+            for serpentine in [false, true] {
+                let mut buffer = source.clone();
+                dither_to_palette(
+                    &mut buffer,
+                    width,
+                    height,
+                    &palette,
+                    method,
+                    1.0,
+                    serpentine,
+                )
+                .unwrap();
+                for (out, src) in buffer.chunks(4).zip(source.chunks(4)) {
+                    assert_eq!(out[3], src[3], "{method:?}");
+                    if src[3] == 0 {
+                        assert_eq!(out, src, "{method:?}");
+                    } else {
+                        assert!(
+                            palette.chunks(3).any(|c| c == &out[..3]),
+                            "{method:?}: {out:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
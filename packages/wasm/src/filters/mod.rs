@@ -0,0 +1,6 @@
+// ぼかし（ガウス・ボックス）
+pub mod blur;
+// パレットへの減色（ディザリング）
+pub mod dither;
+// 減色とパレットの抽出（median cut・k-means・octree）
+pub mod quantize;
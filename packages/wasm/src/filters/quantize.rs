@@ -0,0 +1,640 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+use super::dither::{DitherMethod, dither_to_palette};
+use crate::utils::color::{oklab_to_srgb_bytes, srgb_bytes_to_oklab};
+use crate::utils::validate::check_rgba_len;
+
+/// 抽出できるパレットの色数の上限
+pub const MAX_PALETTE_COLORS: u32 = 256;
+
+// k-means で扱う色の数の上限（これを超えると 5bit/チャンネルにまとめてから扱う）
+const KMEANS_MAX_POINTS: usize = 32768;
+// k-means の反復回数の上限
+const KMEANS_MAX_ITERATIONS: usize = 24;
+// 色相順に並べるとき、これより彩度 (OKLab の chroma) が低い色は無彩色として先頭にまとめる
+const ACHROMATIC_CHROMA: f32 = 0.02;
+
+/// 減色（パレット抽出）の方法
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuantizeMethod {
+    /// 色の分布を最も広いチャンネルの中央値で分割していく
+    MedianCut = 0,
+    /// OKLab 空間での k-means（知覚的に近い色をまとめる。最も遅い）
+    KMeansOklab = 1,
+    /// 8 分木で色を集め、使用頻度の低い枝から統合する（最も速い）
+    Octree = 2,
+}
+
+/// 抽出したパレットの並び順
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaletteSort {
+    /// 使われているピクセル数の多い順
+    Frequency = 0,
+    /// 無彩色（明るさ順）のあとに色相順
+    Hue = 1,
+}
+
+// 画像中の色とそのピクセル数
+#[derive(Clone, Copy)]
+struct ColorCount {
+    rgb: [u8; 3],
+    count: u64,
+}
+
+/// 画像から最大 color_count 色のパレットを抽出する
+/// method: 減色の方法
+/// sort: パレットの並び順
+///
+/// 戻り値は [r, g, b, r, g, b, ...]（dither_to_palette などにそのまま渡せる）。
+/// 完全に透明なピクセルは数えず、画像に含まれる色が color_count より少なければその色数になる。
+/// 結果は入力だけで決まる（乱数は使わない）
+#[wasm_bindgen]
+pub fn extract_palette(
+    buffer: &[u8],
+    width: u32,
+    height: u32,
+    color_count: u32,
+    method: QuantizeMethod,
+    sort: PaletteSort,
+) -> Result<Vec<u8>, JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    check_color_count(color_count)?;
+
+    let palette = build_palette(buffer, color_count as usize, method, sort);
+    Ok(palette.iter().flat_map(|c| c.rgb).collect())
+}
+
+/// 画像からパレットを抽出し、画像をそのパレットの色に置き換える（その場で書き換える）
+/// dither: 置き換えるときのディザリング（None なら最も近い色にするだけ）
+///
+/// 戻り値は抽出したパレット（extract_palette と同じ形式）。
+/// アルファはそのまま残し、完全に透明なピクセルは変えない
+#[wasm_bindgen]
+pub fn quantize_image(
+    buffer: &mut [u8],
+    width: u32,
+    height: u32,
+    color_count: u32,
+    method: QuantizeMethod,
+    sort: PaletteSort,
+    dither: DitherMethod,
+) -> Result<Vec<u8>, JsError> {
+    let palette = extract_palette(buffer, width, height, color_count, method, sort)?;
+    // 不透明なピクセルがなければ置き換えるものもない
+    if !palette.is_empty() {
+        dither_to_palette(buffer, width, height, &palette, dither, 1.0, true)?;
+    }
+    Ok(palette)
+}
+
+fn check_color_count(color_count: u32) -> Result<(), JsError> {
+    if color_count == 0 || color_count > MAX_PALETTE_COLORS {
+        return Err(JsError::new(&format!(
+            "color_count: {} must be between 1 and {}",
+            color_count, MAX_PALETTE_COLORS
+        )));
+    }
+    Ok(())
+}
+
+fn build_palette(
+    buffer: &[u8],
+    n: usize,
+    method: QuantizeMethod,
+    sort: PaletteSort,
+) -> Vec<ColorCount> {
+    let mut colors = histogram(buffer);
+    if colors.is_empty() {
+        return Vec::new();
+    }
+
+    let palette = if colors.len() <= n {
+        colors
+    } else {
+        match method {
+            QuantizeMethod::MedianCut => median_cut(&mut colors, n),
+            QuantizeMethod::KMeansOklab => kmeans_oklab(&colors, n),
+            QuantizeMethod::Octree => octree(&colors, n),
+        }
+    };
+
+    let mut palette = merge_duplicates(palette);
+    sort_palette(&mut palette, sort);
+    palette
+}
+
+// 不透明度が 0 でないピクセルの色ごとの数（色の順に並べて返す）
+fn histogram(buffer: &[u8]) -> Vec<ColorCount> {
+    let mut counts: HashMap<u32, u64> = HashMap::new();
+    // 塗りつぶした範囲では同じ色が続くので、連続する同じ色はまとめて数える
+    let mut run: Option<(u32, u64)> = None;
+    for px in buffer.chunks_exact(4) {
+        if px[3] == 0 {
+            continue;
+        }
+        let key = (px[0] as u32) << 16 | (px[1] as u32) << 8 | px[2] as u32;
+        match &mut run {
+            Some((k, n)) if *k == key => *n += 1,
+            _ => {
+                if let Some((k, n)) = run.replace((key, 1)) {
+                    *counts.entry(k).or_insert(0) += n;
+                }
+            }
+        }
+    }
+    if let Some((k, n)) = run {
+        *counts.entry(k).or_insert(0) += n;
+    }
+    // HashMap の順序は実行ごとに変わるので、結果を決定的にするため並べ直す
+    let mut colors: Vec<ColorCount> = counts
+        .into_iter()
+        .map(|(key, count)| ColorCount {
+            rgb: [(key >> 16) as u8, (key >> 8) as u8, key as u8],
+            count,
+        })
+        .collect();
+    colors.sort_unstable_by_key(|c| c.rgb);
+    colors
+}
+
+// 重み付き平均の色
+fn mean_color(sum: [u64; 3], count: u64) -> [u8; 3] {
+    sum.map(|s| ((s + count / 2) / count) as u8)
+}
+
+// median cut（最も広がりの大きい箱を、その最も広いチャンネルの重み付き中央値で二分する）
+fn median_cut(colors: &mut [ColorCount], n: usize) -> Vec<ColorCount> {
+    let mut boxes = vec![CutBox::new(colors, 0, colors.len())];
+
+    while boxes.len() < n {
+        let Some(index) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.end - b.start > 1)
+            .max_by_key(|&(i, b)| (b.range, b.count, Reverse(i)))
+            .map(|(i, _)| i)
+        else {
+            break;
+        };
+
+        let CutBox {
+            start,
+            end,
+            channel,
+            count,
+            ..
+        } = boxes[index];
+        let slice = &mut colors[start..end];
+        slice.sort_unstable_by_key(|c| (c.rgb[channel], c.rgb));
+        let mut acc = 0;
+        let mut split = slice.len() - 1;
+        for (i, c) in slice.iter().enumerate() {
+            acc += c.count;
+            if acc * 2 >= count {
+                split = i + 1;
+                break;
+            }
+        }
+        // どちらの箱も空にしない
+        let split = start + split.clamp(1, slice.len() - 1);
+        boxes[index] = CutBox::new(colors, start, split);
+        boxes.push(CutBox::new(colors, split, end));
+    }
+
+    boxes
+        .iter()
+        .map(|b| ColorCount {
+            rgb: mean_color(b.sum, b.count),
+            count: b.count,
+        })
+        .collect()
+}
+
+// median cut の箱（colors の範囲 [start, end) と、その色の集計）
+#[derive(Clone, Copy)]
+struct CutBox {
+    start: usize,
+    end: usize,
+    // 最も値の範囲が広いチャンネルとその範囲
+    channel: usize,
+    range: u8,
+    sum: [u64; 3],
+    count: u64,
+}
+
+impl CutBox {
+    fn new(colors: &[ColorCount], start: usize, end: usize) -> Self {
+        let mut min = [u8::MAX; 3];
+        let mut max = [0u8; 3];
+        let mut sum = [0u64; 3];
+        let mut count = 0;
+        for c in &colors[start..end] {
+            for ch in 0..3 {
+                min[ch] = min[ch].min(c.rgb[ch]);
+                max[ch] = max[ch].max(c.rgb[ch]);
+                sum[ch] += c.rgb[ch] as u64 * c.count;
+            }
+            count += c.count;
+        }
+        let (channel, range) = (0..3)
+            .map(|ch| (ch, max[ch] - min[ch]))
+            .max_by_key(|&(ch, range)| (range, Reverse(ch)))
+            .unwrap_or((0, 0));
+        CutBox {
+            start,
+            end,
+            channel,
+            range,
+            sum,
+            count,
+        }
+    }
+}
+
+#[inline]
+fn lab_distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let (dl, da, db) = (a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+    dl * dl + da * da + db * db
+}
+
+// OKLab 空間での重み付き k-means
+fn kmeans_oklab(colors: &[ColorCount], n: usize) -> Vec<ColorCount> {
+    let points = kmeans_points(colors);
+
+    // 初期値: 最も多い色から始め、既存の中心から遠く（かつ多い）色を順に選ぶ（k-means++ を決定的にしたもの）
+    let mut centers: Vec<[f32; 3]> = Vec::with_capacity(n);
+    let first = points
+        .iter()
+        .enumerate()
+        .max_by_key(|&(i, &(_, weight))| (weight, Reverse(i)))
+        .map(|(_, &(lab, _))| lab)
+        .unwrap_or([0.0; 3]);
+    centers.push(first);
+    let mut nearest: Vec<f32> = points
+        .iter()
+        .map(|&(lab, _)| lab_distance(lab, first))
+        .collect();
+    while centers.len() < n {
+        let (index, score) = nearest
+            .iter()
+            .zip(&points)
+            .map(|(&d, &(_, weight))| d * weight as f32)
+            .enumerate()
+            .fold(
+                (0, 0.0f32),
+                |best, (i, s)| if s > best.1 { (i, s) } else { best },
+            );
+        // 残りの色はすべて既存の中心と同じ
+        if score <= 0.0 {
+            break;
+        }
+        let center = points[index].0;
+        centers.push(center);
+        for (d, &(lab, _)) in nearest.iter_mut().zip(&points) {
+            *d = d.min(lab_distance(lab, center));
+        }
+    }
+
+    let mut assignment = vec![usize::MAX; points.len()];
+    let mut weights = vec![0u64; centers.len()];
+    for _ in 0..KMEANS_MAX_ITERATIONS {
+        let mut changed = false;
+        let mut sums = vec![[0.0f64; 3]; centers.len()];
+        weights.iter_mut().for_each(|w| *w = 0);
+
+        for (i, &(lab, weight)) in points.iter().enumerate() {
+            let mut best = 0;
+            let mut best_dist = f32::MAX;
+            for (k, &center) in centers.iter().enumerate() {
+                let dist = lab_distance(lab, center);
+                if dist < best_dist {
+                    best_dist = dist;
+                    best = k;
+                }
+            }
+            if assignment[i] != best {
+                assignment[i] = best;
+                changed = true;
+            }
+            for (s, &v) in sums[best].iter_mut().zip(&lab) {
+                *s += v as f64 * weight as f64;
+            }
+            weights[best] += weight;
+        }
+
+        // 空になった中心はそのまま残す（最後に取り除く）
+        for (k, center) in centers.iter_mut().enumerate() {
+            if weights[k] > 0 {
+                *center = sums[k].map(|s| (s / weights[k] as f64) as f32);
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    centers
+        .iter()
+        .zip(&weights)
+        .filter(|&(_, &weight)| weight > 0)
+        .map(|(&center, &count)| ColorCount {
+            rgb: oklab_to_srgb_bytes(center),
+            count,
+        })
+        .collect()
+}
+
+// k-means に使う点（OKLab の色と重み）
+// 色が多すぎるときは 5bit/チャンネルの箱ごとに OKLab で平均してから使う
+fn kmeans_points(colors: &[ColorCount]) -> Vec<([f32; 3], u64)> {
+    if colors.len() <= KMEANS_MAX_POINTS {
+        return colors
+            .iter()
+            .map(|c| (srgb_bytes_to_oklab(c.rgb), c.count))
+            .collect();
+    }
+
+    let mut sums = vec![([0.0f64; 3], 0u64); 1 << 15];
+    for c in colors {
+        let key =
+            (c.rgb[0] as usize >> 3) << 10 | (c.rgb[1] as usize >> 3) << 5 | c.rgb[2] as usize >> 3;
+        let lab = srgb_bytes_to_oklab(c.rgb);
+        let (sum, count) = &mut sums[key];
+        for (s, &v) in sum.iter_mut().zip(&lab) {
+            *s += v as f64 * c.count as f64;
+        }
+        *count += c.count;
+    }
+    sums.into_iter()
+        .filter(|&(_, count)| count > 0)
+        .map(|(sum, count)| (sum.map(|s| (s / count as f64) as f32), count))
+        .collect()
+}
+
+// 8 分木の節点（色の合計は経路上のすべての節点に足しておく）
+struct OctreeNode {
+    // 子の番号（0 は子なし。根は 0 番なので子になることはない）
+    children: [u32; 8],
+    sum: [u64; 3],
+    count: u64,
+    level: u8,
+}
+
+// octree 量子化（深い階層の、ピクセル数の少ない節点から子を統合して葉を減らす）
+fn octree(colors: &[ColorCount], n: usize) -> Vec<ColorCount> {
+    let mut nodes = vec![OctreeNode {
+        children: [0; 8],
+        sum: [0; 3],
+        count: 0,
+        level: 0,
+    }];
+    let mut leaves = 0usize;
+
+    for c in colors {
+        let mut node = 0;
+        for level in 0..=8u8 {
+            let current = &mut nodes[node];
+            for (s, &v) in current.sum.iter_mut().zip(&c.rgb) {
+                *s += v as u64 * c.count;
+            }
+            current.count += c.count;
+            if level == 8 {
+                break;
+            }
+
+            let shift = 7 - level;
+            let child = ((c.rgb[0] >> shift) & 1) << 2
+                | ((c.rgb[1] >> shift) & 1) << 1
+                | ((c.rgb[2] >> shift) & 1);
+            let next = nodes[node].children[child as usize];
+            node = if next != 0 {
+                next as usize
+            } else {
+                let index = nodes.len();
+                nodes.push(OctreeNode {
+                    children: [0; 8],
+                    sum: [0; 3],
+                    count: 0,
+                    level: level + 1,
+                });
+                nodes[node].children[child as usize] = index as u32;
+                if level + 1 == 8 {
+                    leaves += 1;
+                }
+                index
+            };
+        }
+    }
+
+    // 深い階層から順に統合するので、統合する時点で子はすべて葉になっている
+    let mut reducible: Vec<usize> = (0..nodes.len()).filter(|&i| nodes[i].level < 8).collect();
+    reducible.sort_unstable_by_key(|&i| (Reverse(nodes[i].level), nodes[i].count, i));
+    for i in reducible {
+        if leaves <= n {
+            break;
+        }
+        let mut children: Vec<usize> = nodes[i]
+            .children
+            .iter()
+            .enumerate()
+            .filter(|&(_, &c)| c != 0)
+            .map(|(slot, _)| slot)
+            .collect();
+        if children.len() - 1 <= leaves - n {
+            leaves -= children.len() - 1;
+            nodes[i].children = [0; 8];
+            continue;
+        }
+
+        // すべて統合すると n 色を下回るときは、ピクセル数の少ない子だけを 1 つにまとめる
+        children
+            .sort_unstable_by_key(|&slot| (nodes[nodes[i].children[slot] as usize].count, slot));
+        let merge = &children[..leaves - n + 1];
+        let target = nodes[i].children[merge[0]] as usize;
+        for &slot in &merge[1..] {
+            let child = nodes[i].children[slot] as usize;
+            let (sum, count) = (nodes[child].sum, nodes[child].count);
+            for (s, v) in nodes[target].sum.iter_mut().zip(sum) {
+                *s += v;
+            }
+            nodes[target].count += count;
+            nodes[i].children[slot] = 0;
+        }
+        leaves = n;
+    }
+
+    let mut palette = Vec::with_capacity(leaves);
+    let mut stack = vec![0usize];
+    while let Some(i) = stack.pop() {
+        let node = &nodes[i];
+        if node.children.iter().all(|&c| c == 0) {
+            palette.push(ColorCount {
+                rgb: mean_color(node.sum, node.count),
+                count: node.count,
+            });
+        } else {
+            stack.extend(
+                node.children
+                    .iter()
+                    .rev()
+                    .filter(|&&c| c != 0)
+                    .map(|&c| c as usize),
+            );
+        }
+    }
+    palette
+}
+
+// 平均すると同じ色になった項目をまとめる
+fn merge_duplicates(mut palette: Vec<ColorCount>) -> Vec<ColorCount> {
+    palette.sort_unstable_by_key(|c| c.rgb);
+    let mut merged: Vec<ColorCount> = Vec::with_capacity(palette.len());
+    for c in palette {
+        match merged.last_mut() {
+            Some(last) if last.rgb == c.rgb => last.count += c.count,
+            _ => merged.push(c),
+        }
+    }
+    merged
+}
+
+fn sort_palette(palette: &mut [ColorCount], sort: PaletteSort) {
+    match sort {
+        PaletteSort::Frequency => palette.sort_by_key(|c| (Reverse(c.count), c.rgb)),
+        PaletteSort::Hue => palette.sort_by(|a, b| {
+            let (ka, kb) = (hue_key(a.rgb), hue_key(b.rgb));
+            ka.0.cmp(&kb.0)
+                .then(ka.1.total_cmp(&kb.1))
+                .then(ka.2.total_cmp(&kb.2))
+                .then(a.rgb.cmp(&b.rgb))
+        }),
+    }
+}
+
+// 色相順の並べ替えのキー（有彩色か, 色相, 明るさ）
+fn hue_key(rgb: [u8; 3]) -> (bool, f32, f32) {
+    let [l, a, b] = srgb_bytes_to_oklab(rgb);
+    if a.hypot(b) < ACHROMATIC_CHROMA {
+        return (false, 0.0, l);
+    }
+    let hue = b.atan2(a).rem_euclid(std::f32::consts::TAU);
+    (true, hue, l)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METHODS: [QuantizeMethod; 3] = [
+        QuantizeMethod::MedianCut,
+        QuantizeMethod::KMeansOklab,
+        QuantizeMethod::Octree,
+    ];
+    const SORTS: [PaletteSort; 2] = [PaletteSort::Frequency, PaletteSort::Hue];
+
+    // 32x32 の多色の画像（線形合同法による疑似乱数、一部は透明）
+    fn noise_image() -> Vec<u8> {
+        let mut state = 12345u32;
+        let mut next = || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) as u8
+        };
+        (0..32 * 32)
+            .flat_map(|i| {
+                let alpha = if i % 7 == 0 { 0 } else { 255 };
+                [next(), next(), next(), alpha]
+            })
+            .collect()
+    }
+
+    fn colors(palette: &[u8]) -> Vec<[u8; 3]> {
+        let mut colors: Vec<[u8; 3]> = palette
+            .chunks_exact(3)
+            .map(|c| [c[0], c[1], c[2]])
+            .collect();
+        colors.sort_unstable();
+        colors
+    }
+
+    #[test]
+    fn palette_is_not_larger_than_requested() {
+        let image = noise_image();
+        for method in METHODS {
+            for n in [1, 2, 5, 16, 64, 256] {
+                let palette =
+                    extract_palette(&image, 32, 32, n, method, PaletteSort::Frequency).unwrap();
+                assert_eq!(palette.len() % 3, 0);
+                let len = palette.len() / 3;
+                assert!(len >= 1 && len <= n as usize, "{method:?} n={n}: {len}");
+            }
+        }
+    }
+
+    #[test]
+    fn output_is_deterministic() {
+        let image = noise_image();
+        for method in METHODS {
+            for sort in SORTS {
+                let first = extract_palette(&image, 32, 32, 12, method, sort).unwrap();
+                for _ in 0..3 {
+                    assert_eq!(
+                        extract_palette(&image, 32, 32, 12, method, sort).unwrap(),
+                        first,
+                        "{method:?} {sort:?}"
+                    );
+                }
+
+                let mut a = image.clone();
+                let mut b = image.clone();
+                let dither = DitherMethod::FloydSteinberg;
+                quantize_image(&mut a, 32, 32, 12, method, sort, dither).unwrap();
+                quantize_image(&mut b, 32, 32, 12, method, sort, dither).unwrap();
+                assert_eq!(a, b, "{method:?} {sort:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn few_colors_come_back_exact() {
+        let used = [
+            [255, 0, 0],
+            [0, 128, 0],
+            [10, 20, 30],
+            [250, 250, 250],
+            [0, 0, 0],
+        ];
+        let image: Vec<u8> = (0..40)
+            .flat_map(|i| {
+                let [r, g, b] = used[i % used.len()];
+                [r, g, b, 255]
+            })
+            .collect();
+        let mut expected = used.to_vec();
+        expected.sort_unstable();
+
+        for method in METHODS {
+            for n in [5, 8, 256] {
+                let palette = extract_palette(&image, 8, 5, n, method, PaletteSort::Hue).unwrap();
+                assert_eq!(colors(&palette), expected, "{method:?} n={n}");
+
+                let mut quantized = image.clone();
+                let dither = DitherMethod::FloydSteinberg;
+                quantize_image(&mut quantized, 8, 5, n, method, PaletteSort::Hue, dither).unwrap();
+                assert_eq!(quantized, image, "{method:?} n={n}");
+            }
+        }
+    }
+
+    #[test]
+    fn transparent_image_has_no_palette() {
+        let image = [0u8; 16];
+        for method in METHODS {
+            let palette = extract_palette(&image, 2, 2, 4, method, PaletteSort::Frequency).unwrap();
+            assert!(palette.is_empty());
+        }
+    }
+}
@@ -6,6 +6,14 @@ pub mod webgl;
 pub mod selection;
 // ユーティリティ
 pub mod utils;
+// エクスポート関連
+pub mod export;
+// ピクセルバッファ操作
+pub mod buffer;
+// レイヤー合成（WebGLシェーダーのCPU版）
+pub mod compositor;
+// フィルター（エフェクト）
+pub mod filters;
 
 #[wasm_bindgen]
 extern "C" {
@@ -1,5 +1,7 @@
 use wasm_bindgen::prelude::*;
 
+use crate::utils::validate::{check_rgba_len, pixel_count};
+
 /// しきい値付きの自動選択（領域抽出）
 /// 入力バッファは RGBA 連続の &[u8]。変更せず、選択マスク(幅*高さ, 0/1)を返す。
 #[wasm_bindgen]
@@ -11,19 +13,22 @@ pub fn auto_select_region_mask(
     start_y: u32,
     threshold: u8,
     _connectivity: u8, // 予備（4/8連結）。現状は4連結相当。
-) -> Vec<u8> {
+) -> Result<Vec<u8>, JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    let total = pixel_count(width, height)?;
+
     let width = width as usize;
     let height = height as usize;
     let sx = start_x as usize;
     let sy = start_y as usize;
 
     // 範囲外は空マスク
-    if sx >= width || sy >= height || width == 0 || height == 0 {
-        return vec![0; width.saturating_mul(height)];
+    if sx >= width || sy >= height {
+        return Ok(vec![0; total]);
     }
 
     if threshold == 255 {
-        return vec![1; width * height];
+        return Ok(vec![1; total]);
     }
 
     let start_index = (sy * width + sx) * 4;
@@ -137,7 +142,7 @@ pub fn auto_select_region_mask(
         }
     }
 
-    mask
+    Ok(mask)
 }
 
 #[inline]
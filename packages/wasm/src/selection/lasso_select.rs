@@ -1,5 +1,16 @@
 use wasm_bindgen::prelude::*;
 
+use crate::selection::mask_filter::SelectionLimitMode;
+use crate::utils::validate::check_mask_len;
+
+/// ポリゴンの塗りつぶし規則
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillRule {
+    EvenOdd = 0,
+    NonZero = 1,
+}
+
 /// Lasso選択のためのスキャンライン塗りつぶし実装
 ///
 /// この実装は以下の特徴を持ちます：
@@ -13,15 +24,18 @@ pub fn fill_lasso_selection(
     mask: &mut [u8],
     width: u32,
     height: u32,
-    points: &[f32],  // [x1, y1, x2, y2, ...]
-    fill_rule: &str, // "evenodd" or "nonzero"
-) -> bool {
+    points: &[f32], // [x1, y1, x2, y2, ...]
+    fill_rule: FillRule,
+) -> Result<bool, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+
     let width = width as usize;
     let height = height as usize;
 
     if points.len() < 6 || !points.len().is_multiple_of(2) {
-        return false; // 最低3点必要
+        return Ok(false); // 最低3点必要
     }
+    check_points(points)?;
 
     // ポリゴンの点を構築
     let polygon: Vec<(f32, f32)> = points
@@ -40,14 +54,13 @@ pub fn fill_lasso_selection(
 
     // スキャンライン方式でポリゴン内部を塗りつぶし
     match fill_rule {
-        "nonzero" => {
+        FillRule::NonZero => {
             for y in start_y..end_y {
                 let intersections = find_intersections_with_direction(&polygon, y as f32);
                 fill_scanline_mask_nonzero(mask, width, y, &intersections, start_x, end_x);
             }
         }
-        _ => {
-            // "evenodd" またはデフォルト
+        FillRule::EvenOdd => {
             for y in start_y..end_y {
                 let intersections = find_intersections(&polygon, y as f32);
                 fill_scanline_mask(mask, width, y, &intersections, start_x, end_x);
@@ -55,7 +68,7 @@ pub fn fill_lasso_selection(
         }
     }
 
-    true
+    Ok(true)
 }
 
 /// 選択範囲制限付きLasso選択
@@ -66,14 +79,18 @@ pub fn fill_lasso_selection_with_mask(
     height: u32,
     points: &[f32],
     existing_mask: &[u8],
-    limit_mode: &str, // "inside", "outside", "none"
-) -> bool {
+    limit_mode: SelectionLimitMode,
+) -> Result<bool, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+    check_mask_len("existing_mask", existing_mask, width, height)?;
+
     let width = width as usize;
     let height = height as usize;
 
     if points.len() < 6 || !points.len().is_multiple_of(2) {
-        return false;
+        return Ok(false);
     }
+    check_points(points)?;
 
     let polygon: Vec<(f32, f32)> = points
         .chunks_exact(2)
@@ -89,16 +106,12 @@ pub fn fill_lasso_selection_with_mask(
 
     // 選択範囲制限チェック関数
     let is_allowed = |x: usize, y: usize| -> bool {
-        let mask_index = y * width + x;
-        if mask_index >= existing_mask.len() {
-            return false;
-        }
-        let is_in_selection = existing_mask[mask_index] > 0;
+        let is_in_selection = existing_mask[y * width + x] > 0;
 
         match limit_mode {
-            "inside" => is_in_selection,
-            "outside" => !is_in_selection,
-            _ => true,
+            SelectionLimitMode::Inside => is_in_selection,
+            SelectionLimitMode::Outside => !is_in_selection,
+            SelectionLimitMode::None => true,
         }
     };
 
@@ -107,7 +120,7 @@ pub fn fill_lasso_selection_with_mask(
         fill_scanline_mask_with_limit(mask, width, y, &intersections, start_x, end_x, &is_allowed);
     }
 
-    true
+    Ok(true)
 }
 
 /// Point-in-polygon アルゴリズムを使用した直接的な実装（小さなポリゴン用）
@@ -117,13 +130,16 @@ pub fn fill_lasso_selection_point_in_polygon(
     width: u32,
     height: u32,
     points: &[f32],
-) -> bool {
+) -> Result<bool, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+
     let width = width as usize;
     let height = height as usize;
 
     if points.len() < 6 || !points.len().is_multiple_of(2) {
-        return false;
+        return Ok(false);
     }
+    check_points(points)?;
 
     let polygon: Vec<(f32, f32)> = points
         .chunks_exact(2)
@@ -145,7 +161,15 @@ pub fn fill_lasso_selection_point_in_polygon(
         }
     }
 
-    true
+    Ok(true)
+}
+
+/// 座標に NaN / 無限大が含まれていないか確認する
+fn check_points(points: &[f32]) -> Result<(), JsError> {
+    if points.iter().any(|v| !v.is_finite()) {
+        return Err(JsError::new("points must contain only finite numbers"));
+    }
+    Ok(())
 }
 
 /// ポリゴンのバウンディングボックスを計算
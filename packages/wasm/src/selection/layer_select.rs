@@ -0,0 +1,167 @@
+use wasm_bindgen::prelude::*;
+
+use crate::utils::mask::MaskFormat;
+use crate::utils::validate::check_layers_len;
+
+/// 複数レイヤーの結果のまとめ方
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayerCombineMode {
+    /// いずれかのレイヤーに内容があれば選択（和）
+    Union = 0,
+    /// すべてのレイヤーに内容がある所だけ選択（積）
+    Intersection = 1,
+}
+
+/// レイヤーの不透明部分を選択範囲マスクにする（レイヤーのCtrl+クリック相当）
+/// layers: 同じサイズのRGBAバッファを layer_count 枚連結したもの
+/// alpha_threshold: この値より大きいアルファを内容とみなす（0 なら透明以外すべて）
+/// format: 戻り値のマスクの形式（Coverage ならアルファをそのまま被覆率にする）
+/// mode: 複数レイヤーの和・積（アルファの最大・最小）
+///
+/// 戻り値は同じ format で combine_masks_* にそのまま渡せるマスク。
+/// Coverage のときもしきい値以下のピクセルは 0 になる
+#[wasm_bindgen]
+pub fn select_layer_opacity(
+    layers: &[u8],
+    width: u32,
+    height: u32,
+    layer_count: u32,
+    alpha_threshold: u8,
+    format: MaskFormat,
+    mode: LayerCombineMode,
+) -> Result<Vec<u8>, JsError> {
+    let layer_len = check_layers_len("layers", layers, width, height, layer_count)?;
+    if layer_count == 0 {
+        return Err(JsError::new("layer_count must be at least 1"));
+    }
+
+    let pixels = layer_len / 4;
+    let layer_count = layer_count as usize;
+    let mut mask = vec![0u8; pixels];
+
+    for (i, m) in mask.iter_mut().enumerate() {
+        let alphas = (0..layer_count).map(|l| layers[l * layer_len + i * 4 + 3]);
+        let alpha = match mode {
+            LayerCombineMode::Union => alphas.max(),
+            LayerCombineMode::Intersection => alphas.min(),
+        }
+        .unwrap_or(0);
+
+        if alpha <= alpha_threshold {
+            continue;
+        }
+        *m = match format {
+            MaskFormat::Binary => 1,
+            MaskFormat::Coverage => alpha,
+        };
+    }
+
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // アルファだけを並べた1枚分の RGBA
+    fn layer(alphas: &[u8]) -> Vec<u8> {
+        alphas.iter().flat_map(|&a| [10, 20, 30, a]).collect()
+    }
+
+    #[test]
+    fn alpha_at_the_threshold_is_not_selected() {
+        let layers = layer(&[0, 100, 101, 255]);
+        let mask = select_layer_opacity(
+            &layers,
+            4,
+            1,
+            1,
+            100,
+            MaskFormat::Binary,
+            LayerCombineMode::Union,
+        )
+        .unwrap();
+        assert_eq!(mask, [0, 0, 1, 1]);
+
+        let mask = select_layer_opacity(
+            &layers,
+            4,
+            1,
+            1,
+            0,
+            MaskFormat::Binary,
+            LayerCombineMode::Union,
+        )
+        .unwrap();
+        assert_eq!(mask, [0, 1, 1, 1]);
+    }
+
+    #[test]
+    fn coverage_keeps_the_alpha() {
+        let layers = layer(&[0, 100, 101, 255]);
+        let mask = select_layer_opacity(
+            &layers,
+            4,
+            1,
+            1,
+            100,
+            MaskFormat::Coverage,
+            LayerCombineMode::Union,
+        )
+        .unwrap();
+        assert_eq!(mask, [0, 0, 101, 255]);
+
+        let mask = select_layer_opacity(
+            &layers,
+            4,
+            1,
+            1,
+            0,
+            MaskFormat::Coverage,
+            LayerCombineMode::Union,
+        )
+        .unwrap();
+        assert_eq!(mask, [0, 100, 101, 255]);
+    }
+
+    #[test]
+    fn layers_combine_by_max_or_min_alpha() {
+        let layers = [layer(&[0, 60, 200, 255]), layer(&[40, 0, 90, 255])].concat();
+        let union = select_layer_opacity(
+            &layers,
+            4,
+            1,
+            2,
+            0,
+            MaskFormat::Coverage,
+            LayerCombineMode::Union,
+        )
+        .unwrap();
+        assert_eq!(union, [40, 60, 200, 255]);
+
+        let intersection = select_layer_opacity(
+            &layers,
+            4,
+            1,
+            2,
+            0,
+            MaskFormat::Coverage,
+            LayerCombineMode::Intersection,
+        )
+        .unwrap();
+        assert_eq!(intersection, [0, 0, 90, 255]);
+
+        let intersection = select_layer_opacity(
+            &layers,
+            4,
+            1,
+            2,
+            0,
+            MaskFormat::Binary,
+            LayerCombineMode::Intersection,
+        )
+        .unwrap();
+        assert_eq!(intersection, [0, 0, 1, 1]);
+    }
+}
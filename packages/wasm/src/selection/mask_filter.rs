@@ -1,29 +1,50 @@
 use wasm_bindgen::prelude::*;
 
+use crate::utils::composite::{blend_source_atop, blend_source_over};
+use crate::utils::mask::MaskFormat;
+use crate::utils::validate::{check_mask_len, check_rgba_len, pixel_count};
+
+/// 選択範囲制限モード
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SelectionLimitMode {
+    /// 制限なし
+    None = 0,
+    /// 選択範囲内のみ
+    Inside = 1,
+    /// 選択範囲外のみ
+    Outside = 2,
+}
+
 /// 選択範囲制限モードに応じてピクセルバッファをフィルタリングする
 /// original_buffer: 元のピクセルバッファ (RGBA)
-/// selection_mask: 選択範囲のマスク (0 or 1)
-/// mode: 制限モード ("inside", "outside", "none")
+/// selection_mask: 選択範囲のマスク
+/// mask_format: selection_mask の形式
+/// mode: 制限モード
 /// width, height: 画像のサイズ
 ///
-/// "inside": 選択範囲外を透明化
-/// "outside": 選択範囲内を透明化
-/// "none": 元のバッファをそのまま返す
+/// Inside: 選択範囲外を透明化（アルファに被覆率を掛ける）
+/// Outside: 選択範囲内を透明化（アルファに 1 - 被覆率 を掛ける）
+/// None: 元のバッファをそのまま返す
 #[wasm_bindgen]
 pub fn filter_by_selection_mask(
     original_buffer: &[u8],
     selection_mask: &[u8],
-    mode: &str,
+    mask_format: MaskFormat,
+    mode: SelectionLimitMode,
     width: u32,
     height: u32,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, JsError> {
+    check_rgba_len("original_buffer", original_buffer, width, height)?;
+
     let w = width as i32;
     let h = height as i32;
 
     // 制限なしの場合は元のバッファをそのまま返す
-    if mode == "none" {
-        return original_buffer.to_vec();
+    if mode == SelectionLimitMode::None {
+        return Ok(original_buffer.to_vec());
     }
+    check_mask_len("selection_mask", selection_mask, width, height)?;
 
     // 結果バッファを初期化（元のバッファをコピー）
     let mut result = original_buffer.to_vec();
@@ -33,54 +54,69 @@ pub fn filter_by_selection_mask(
         for x in 0..w {
             let idx = (y * w + x) as usize;
 
-            if idx < selection_mask.len() {
-                let is_selected = selection_mask[idx] == 1;
-                let should_make_transparent = match mode {
-                    "inside" => !is_selected, // 選択範囲外を透明化
-                    "outside" => is_selected, // 選択範囲内を透明化
-                    _ => false,               // "none"やその他の場合
-                };
-
-                if should_make_transparent {
-                    let pixel_start = idx * 4;
-                    if pixel_start + 3 < result.len() {
-                        // ピクセルを透明化
-                        result[pixel_start] = 0; // R
-                        result[pixel_start + 1] = 0; // G
-                        result[pixel_start + 2] = 0; // B
-                        result[pixel_start + 3] = 0; // A (完全透明)
-                    }
+            let coverage = mask_format.coverage(selection_mask[idx]);
+            let keep = match mode {
+                SelectionLimitMode::Inside => coverage, // 選択範囲外を透明化
+                SelectionLimitMode::Outside => 255 - coverage, // 選択範囲内を透明化
+                SelectionLimitMode::None => 255,
+            };
+
+            let pixel_start = idx * 4;
+            if keep < 255 {
+                let alpha = (result[pixel_start + 3] as u32 * keep as u32 + 127) / 255;
+                if alpha == 0 {
+                    // ピクセルを透明化
+                    result[pixel_start] = 0; // R
+                    result[pixel_start + 1] = 0; // G
+                    result[pixel_start + 2] = 0; // B
                 }
+                result[pixel_start + 3] = alpha as u8;
             }
         }
     }
 
-    result
+    Ok(result)
 }
 
 /// 2つのバッファを合成する（FloodFill結果を元のバッファに適用）
 /// base_buffer: ベースとなるピクセルバッファ (RGBA)
 /// overlay_buffer: 重ねるピクセルバッファ (RGBA) - FloodFillの結果
-/// selection_mask: 選択範囲のマスク (0 or 1)
-/// mode: 制限モード ("inside", "outside", "none")
+/// selection_mask: 選択範囲のマスク
+/// mask_format: selection_mask の形式
+/// mode: 制限モード
 /// width, height: 画像のサイズ
+/// alpha_lock: true なら透明部分を保護する（source-atop で重ね、ベースのアルファを変えない）
+///
+/// overlay は source-over でベースに重ね、そのアルファに被覆率を掛ける
+/// (Outside では 1 - 被覆率、None では常に 1)。
+/// ベースと同じピクセル（FloodFillで変化していない所）は重ねない。
+/// None で alpha_lock も false なら overlay をそのまま返す（selection_mask は見ない）
 #[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn composite_fill_result(
     base_buffer: &[u8],
     overlay_buffer: &[u8],
     selection_mask: &[u8],
-    mode: &str,
+    mask_format: MaskFormat,
+    mode: SelectionLimitMode,
     width: u32,
     height: u32,
-) -> Vec<u8> {
-    let w = width as i32;
-    let h = height as i32;
+    alpha_lock: bool,
+) -> Result<Vec<u8>, JsError> {
+    check_rgba_len("base_buffer", base_buffer, width, height)?;
+    check_rgba_len("overlay_buffer", overlay_buffer, width, height)?;
+    if mode != SelectionLimitMode::None {
+        check_mask_len("selection_mask", selection_mask, width, height)?;
+    }
 
-    // 制限なしの場合はoverlayをそのまま返す
-    if mode == "none" {
-        return overlay_buffer.to_vec();
+    // 制限なしで透明部分も保護しないなら overlay をそのまま返す
+    if mode == SelectionLimitMode::None && !alpha_lock {
+        return Ok(overlay_buffer.to_vec());
     }
 
+    let w = width as i32;
+    let h = height as i32;
+
     // 結果バッファを初期化（ベースバッファをコピー）
     let mut result = base_buffer.to_vec();
 
@@ -89,32 +125,65 @@ pub fn composite_fill_result(
         for x in 0..w {
             let idx = (y * w + x) as usize;
 
-            if idx < selection_mask.len() {
-                let is_selected = selection_mask[idx] == 1;
-                let should_apply = match mode {
-                    "inside" => is_selected,   // 選択範囲内のみ適用
-                    "outside" => !is_selected, // 選択範囲外のみ適用
-                    _ => true,                 // "none"やその他の場合は全て適用
-                };
-
-                if should_apply {
-                    let pixel_start = idx * 4;
-                    if pixel_start + 3 < overlay_buffer.len() && pixel_start + 3 < result.len() {
-                        // overlayのピクセルが透明でない場合のみ適用
-                        let overlay_alpha = overlay_buffer[pixel_start + 3];
-                        if overlay_alpha > 0 {
-                            result[pixel_start] = overlay_buffer[pixel_start];
-                            result[pixel_start + 1] = overlay_buffer[pixel_start + 1];
-                            result[pixel_start + 2] = overlay_buffer[pixel_start + 2];
-                            result[pixel_start + 3] = overlay_buffer[pixel_start + 3];
-                        }
-                    }
-                }
+            let coverage = match mode {
+                SelectionLimitMode::Inside => mask_format.coverage(selection_mask[idx]), // 選択範囲内のみ適用
+                SelectionLimitMode::Outside => 255 - mask_format.coverage(selection_mask[idx]), // 選択範囲外のみ適用
+                SelectionLimitMode::None => 255, // 全て適用
+            };
+            if coverage == 0 {
+                continue;
+            }
+
+            let pixel_start = idx * 4;
+            let overlay = &overlay_buffer[pixel_start..pixel_start + 4];
+            if overlay == &result[pixel_start..pixel_start + 4] {
+                continue;
+            }
+            let color = [overlay[0], overlay[1], overlay[2], overlay[3]];
+            let dst = &mut result[pixel_start..pixel_start + 4];
+            if alpha_lock {
+                blend_source_atop(dst, color, coverage);
+            } else {
+                blend_source_over(dst, color, coverage);
             }
         }
     }
 
-    result
+    Ok(result)
+}
+
+/// 描画・エフェクト後のバッファに描画前のアルファを戻す（透明部分の保護）
+/// buffer: 描画後のピクセルバッファ (RGBA)。その場で書き換える
+/// original_buffer: 描画前のピクセルバッファ (RGBA)
+/// width, height: 画像のサイズ
+///
+/// 色は描画後のものを使い、アルファだけを描画前に戻す。描画前に透明だったピクセルは透明のまま、
+/// 描画後に透明になったピクセルは描画前の色に戻す。
+/// 不透明なピクセルでは source-atop で描いたのと同じ結果になる（半透明部分は近似）
+#[wasm_bindgen]
+pub fn preserve_alpha(
+    buffer: &mut [u8],
+    original_buffer: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<(), JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    check_rgba_len("original_buffer", original_buffer, width, height)?;
+
+    for (px, orig) in buffer
+        .chunks_exact_mut(4)
+        .zip(original_buffer.chunks_exact(4))
+    {
+        if orig[3] == 0 {
+            px.fill(0);
+        } else if px[3] == 0 {
+            px.copy_from_slice(orig);
+        } else {
+            px[3] = orig[3];
+        }
+    }
+
+    Ok(())
 }
 
 #[wasm_bindgen]
@@ -126,14 +195,28 @@ pub fn trim_mask_with_box(
     box_y: u32,
     box_width: u32,
     box_height: u32,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, JsError> {
+    check_mask_len("mask", mask, mask_width, mask_height)?;
+
+    // Box must lie inside the mask
+    let fits_x = box_x
+        .checked_add(box_width)
+        .is_some_and(|r| r <= mask_width);
+    let fits_y = box_y
+        .checked_add(box_height)
+        .is_some_and(|b| b <= mask_height);
+    if !fits_x || !fits_y {
+        return Err(JsError::new(&format!(
+            "box ({box_x}, {box_y}, {box_width}x{box_height}) is outside of the {mask_width}x{mask_height} mask"
+        )));
+    }
+
     let mw = mask_width as i32;
-    let _mh = mask_height as i32;
     let bw = box_width as i32;
     let bh = box_height as i32;
 
     // Output mask has same dims as box
-    let mut result = vec![0u8; (box_width as usize) * (box_height as usize)];
+    let mut result = vec![0u8; pixel_count(box_width, box_height)?];
 
     // Offsets
     let ox = box_x as i32;
@@ -150,5 +233,157 @@ pub fn trim_mask_with_box(
         }
     }
 
-    result
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn faint_coverage_stays_faint() {
+        let buffer = [10u8, 20, 30, 255, 10, 20, 30, 255];
+        let mask = [1u8, 255];
+
+        let inside = filter_by_selection_mask(
+            &buffer,
+            &mask,
+            MaskFormat::Coverage,
+            SelectionLimitMode::Inside,
+            2,
+            1,
+        )
+        .unwrap();
+        assert_eq!(inside[3], 1);
+        assert_eq!(inside[7], 255);
+
+        let binary = filter_by_selection_mask(
+            &buffer,
+            &mask,
+            MaskFormat::Binary,
+            SelectionLimitMode::Inside,
+            2,
+            1,
+        )
+        .unwrap();
+        assert_eq!(binary, buffer);
+    }
+
+    #[test]
+    fn fill_applies_coverage() {
+        let base = [0u8, 0, 0, 255];
+        let overlay = [255u8, 255, 255, 255];
+
+        let faint = composite_fill_result(
+            &base,
+            &overlay,
+            &[1],
+            MaskFormat::Coverage,
+            SelectionLimitMode::Inside,
+            1,
+            1,
+            false,
+        )
+        .unwrap();
+        assert_eq!(faint, [1, 1, 1, 255]);
+
+        let full = composite_fill_result(
+            &base,
+            &overlay,
+            &[1],
+            MaskFormat::Binary,
+            SelectionLimitMode::Inside,
+            1,
+            1,
+            false,
+        )
+        .unwrap();
+        assert_eq!(full, overlay);
+    }
+
+    #[test]
+    fn no_limit_returns_the_overlay() {
+        // 透明で塗りつぶした結果もそのまま反映される（source-over ではアルファが下がらない）
+        let base = [10u8, 20, 30, 255, 40, 50, 60, 255];
+        let overlay = [0u8, 0, 0, 0, 40, 50, 60, 255];
+        let out = composite_fill_result(
+            &base,
+            &overlay,
+            &[],
+            MaskFormat::Binary,
+            SelectionLimitMode::None,
+            2,
+            1,
+            false,
+        )
+        .unwrap();
+        assert_eq!(out, overlay);
+    }
+
+    #[test]
+    fn alpha_lock_keeps_base_alpha() {
+        let base = [0u8, 0, 0, 128, 0, 0, 0, 0, 0, 0, 0, 255];
+        let overlay = [255u8, 255, 255, 255, 255, 255, 255, 255, 255, 0, 0, 255];
+        let out = composite_fill_result(
+            &base,
+            &overlay,
+            &[1, 1, 0],
+            MaskFormat::Binary,
+            SelectionLimitMode::Inside,
+            3,
+            1,
+            true,
+        )
+        .unwrap();
+        // 半透明のピクセルは色だけ変わり、透明なピクセルと選択範囲外は変わらない
+        assert_eq!(out, [255, 255, 255, 128, 0, 0, 0, 0, 0, 0, 0, 255]);
+
+        // 制限なしでも alpha_lock ならベースのアルファを保つ
+        let out = composite_fill_result(
+            &base,
+            &overlay,
+            &[],
+            MaskFormat::Binary,
+            SelectionLimitMode::None,
+            3,
+            1,
+            true,
+        )
+        .unwrap();
+        assert_eq!(out, [255, 255, 255, 128, 0, 0, 0, 0, 255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn alpha_lock_scales_by_coverage() {
+        let base = [0u8, 0, 0, 200];
+        let overlay = [255u8, 255, 255, 255];
+        let out = composite_fill_result(
+            &base,
+            &overlay,
+            &[51],
+            MaskFormat::Coverage,
+            SelectionLimitMode::Inside,
+            1,
+            1,
+            true,
+        )
+        .unwrap();
+        assert_eq!(out, [51, 51, 51, 200]);
+    }
+
+    #[test]
+    fn preserve_alpha_restores_the_original_alpha() {
+        let original = [
+            10u8, 20, 30, 0, // 透明
+            10, 20, 30, 255, // 描画後に消された
+            10, 20, 30, 128, // 描画で色とアルファが変わった
+        ];
+        let mut buffer = [
+            200u8, 100, 50, 255, //
+            200, 100, 50, 0, //
+            200, 100, 50, 255,
+        ];
+        preserve_alpha(&mut buffer, &original, 3, 1).unwrap();
+        assert_eq!(buffer, [0, 0, 0, 0, 10, 20, 30, 255, 200, 100, 50, 128]);
+    }
 }
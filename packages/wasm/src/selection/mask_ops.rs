@@ -1,23 +1,46 @@
 use wasm_bindgen::prelude::*;
 
+use crate::utils::mask::MaskFormat;
+use crate::utils::rect::rects_from_flat;
+use crate::utils::validate::{check_mask_len, check_same_len, pixel_count};
+
 /// マスク合成：OR演算 (add mode)
+/// format: 2つのマスクと戻り値の形式。被覆率のマスクでは大きい方の被覆率を取る
 #[wasm_bindgen]
-pub fn combine_masks_add(base_mask: &[u8], preview_mask: &[u8]) -> Vec<u8> {
+pub fn combine_masks_add(
+    base_mask: &[u8],
+    preview_mask: &[u8],
+    format: MaskFormat,
+) -> Result<Vec<u8>, JsError> {
+    check_same_len("base_mask", base_mask, "preview_mask", preview_mask)?;
+
     let mut result = base_mask.to_vec();
-    for i in 0..result.len().min(preview_mask.len()) {
-        result[i] |= preview_mask[i];
+    for i in 0..result.len() {
+        let coverage = format
+            .coverage(result[i])
+            .max(format.coverage(preview_mask[i]));
+        result[i] = format.from_coverage(coverage);
     }
-    result
+    Ok(result)
 }
 
 /// マスク合成：AND NOT演算 (subtract mode)
+/// format: 2つのマスクと戻り値の形式。被覆率のマスクでは base の被覆率に (1 - preview の被覆率) を掛ける
 #[wasm_bindgen]
-pub fn combine_masks_subtract(base_mask: &[u8], preview_mask: &[u8]) -> Vec<u8> {
+pub fn combine_masks_subtract(
+    base_mask: &[u8],
+    preview_mask: &[u8],
+    format: MaskFormat,
+) -> Result<Vec<u8>, JsError> {
+    check_same_len("base_mask", base_mask, "preview_mask", preview_mask)?;
+
     let mut result = base_mask.to_vec();
-    for i in 0..result.len().min(preview_mask.len()) {
-        result[i] &= preview_mask[i] ^ 1;
+    for i in 0..result.len() {
+        let keep = 255 - format.coverage(preview_mask[i]) as u32;
+        let coverage = (format.coverage(result[i]) as u32 * keep + 127) / 255;
+        result[i] = format.from_coverage(coverage as u8);
     }
-    result
+    Ok(result)
 }
 
 /// マスク合成：置換 (replace mode)
@@ -36,21 +59,44 @@ pub fn fill_rect_mask(
     start_y: u32,
     rect_width: u32,
     rect_height: u32,
-) {
+) -> Result<(), JsError> {
+    check_mask_len("mask", mask, width, height)?;
+
     let w = width as usize;
-    let _h = height as usize;
 
-    for y in start_y..(start_y + rect_height).min(height) {
-        for x in start_x..(start_x + rect_width).min(width) {
+    for y in start_y..start_y.saturating_add(rect_height).min(height) {
+        for x in start_x..start_x.saturating_add(rect_width).min(width) {
             let idx = (y as usize) * w + (x as usize);
-            if idx < mask.len() {
-                mask[idx] = 1;
-            }
+            mask[idx] = 1;
+        }
+    }
+
+    Ok(())
+}
+
+/// 矩形の列からマスクを作る（mask_to_rects の逆）
+/// rects: [x, y, width, height, ...]。画像からはみ出した部分は切り捨てる
+#[wasm_bindgen]
+pub fn rects_to_mask(rects: &[u32], width: u32, height: u32) -> Result<Vec<u8>, JsError> {
+    let rects = rects_from_flat("rects", rects)?;
+
+    let w = width as usize;
+    let mut mask = vec![0u8; pixel_count(width, height)?];
+
+    for rect in &rects {
+        let Some((x0, y0, x1, y1)) = rect.clip_to(width, height) else {
+            continue;
+        };
+        for y in y0..y1 {
+            mask[y * w + x0..y * w + x1].fill(1);
         }
     }
+
+    Ok(mask)
 }
 
 /// マスクオフセット適用（commitOffset用）
+/// 値はそのまま移すので、2値マスクにも被覆率のマスクにも使える
 #[wasm_bindgen]
 pub fn apply_mask_offset(
     mask: &[u8],
@@ -58,27 +104,65 @@ pub fn apply_mask_offset(
     height: u32,
     offset_x: i32,
     offset_y: i32,
-) -> Vec<u8> {
+) -> Result<Vec<u8>, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+
     let w = width as i32;
     let h = height as i32;
-    let mut result = vec![0u8; (width * height) as usize];
+    let mut result = vec![0u8; pixel_count(width, height)?];
 
     for y in 0..h {
         for x in 0..w {
             let old_idx = (y * w + x) as usize;
-            if old_idx < mask.len() && mask[old_idx] == 1 {
-                let new_x = x + offset_x;
-                let new_y = y + offset_y;
+            if mask[old_idx] != 0 {
+                let new_x = x.saturating_add(offset_x);
+                let new_y = y.saturating_add(offset_y);
 
                 if new_x >= 0 && new_x < w && new_y >= 0 && new_y < h {
                     let new_idx = (new_y * w + new_x) as usize;
-                    if new_idx < result.len() {
-                        result[new_idx] = 1;
-                    }
+                    result[new_idx] = mask[old_idx];
                 }
             }
         }
     }
 
-    result
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_combine_stays_binary() {
+        let base = [0u8, 1, 1, 0];
+        let preview = [0u8, 0, 1, 1];
+
+        let add = combine_masks_add(&base, &preview, MaskFormat::Binary).unwrap();
+        assert_eq!(add, [0, 1, 1, 1]);
+        let subtract = combine_masks_subtract(&base, &preview, MaskFormat::Binary).unwrap();
+        assert_eq!(subtract, [0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn coverage_combine_uses_coverage() {
+        let base = [1u8, 128, 255, 255];
+        let preview = [0u8, 64, 128, 1];
+
+        let add = combine_masks_add(&base, &preview, MaskFormat::Coverage).unwrap();
+        assert_eq!(add, [1, 128, 255, 255]);
+        let subtract = combine_masks_subtract(&base, &preview, MaskFormat::Coverage).unwrap();
+        assert_eq!(subtract, [1, 96, 127, 254]);
+    }
+
+    #[test]
+    fn offset_moves_soft_values() {
+        let mask = [0u8, 1, 128, 255, 0, 0];
+
+        let moved = apply_mask_offset(&mask, 3, 2, 0, 1).unwrap();
+        assert_eq!(moved, [0, 0, 0, 0, 1, 128]);
+
+        let moved = apply_mask_offset(&mask, 3, 2, -1, 0).unwrap();
+        assert_eq!(moved, [1, 128, 0, 0, 0, 0]);
+    }
 }
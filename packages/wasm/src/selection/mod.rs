@@ -8,3 +8,7 @@ pub mod mask_filter;
 pub mod auto_select;
 // 投げ縄選択(lasso)
 pub mod lasso_select;
+// 選択範囲の縁取り(stroke)
+pub mod stroke;
+// レイヤーの不透明部分から選択
+pub mod layer_select;
@@ -1,287 +1,1714 @@
+use std::fmt::Write;
 use wasm_bindgen::prelude::*;
 
-// 座標型
+use crate::utils::validate::{check_finite, check_mask_len};
+
+// 座標型（頂点グリッド上の整数座標）
 #[derive(Clone, Copy, Debug, PartialEq)]
-struct Point {
-    x: i32,
-    y: i32,
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// アウトラインのループ種別
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum LoopKind {
+    /// 外周（選択範囲の外側の境界）
+    Outer = 0,
+    /// 穴（選択範囲内の非選択領域の境界）
+    Hole = 1,
 }
 
-// 直線セグメント型
+/// 1本の閉じたアウトライン（角の頂点のみ）
 #[derive(Clone, Debug)]
-struct Segment {
-    p1: Point,
-    p2: Point,
+pub struct OutlineLoop {
+    pub points: Vec<Point>,
+    pub kind: LoopKind,
 }
 
-/// 選択範囲マスクからSVGパス文字列を生成
+/// 構造化されたアウトライン
+/// vertices: 全ループの頂点を連結した [x0, y0, x1, y1, ...]
+/// offsets: 各ループの開始位置（頂点単位）。末尾に総頂点数が入るので長さはループ数 + 1
+/// kinds: 各ループの種別 (LoopKind)
 #[wasm_bindgen]
-pub fn mask_to_path(mask: &[u8], width: u32, height: u32, offset_x: f32, offset_y: f32) -> String {
-    let w = width as i32;
-    let h = height as i32;
+pub struct OutlinePolylines {
+    vertices: Vec<f32>,
+    offsets: Vec<u32>,
+    kinds: Vec<u8>,
+}
 
-    // 1. 境界セグメント抽出
-    let raw_segments = extract_boundary_segments(mask, w, h);
+#[wasm_bindgen]
+impl OutlinePolylines {
+    #[wasm_bindgen(getter)]
+    pub fn vertices(&self) -> Vec<f32> {
+        self.vertices.clone()
+    }
 
-    // 2. セグメントマージ
-    let merged_segments = merge_segments(raw_segments);
+    #[wasm_bindgen(getter)]
+    pub fn offsets(&self) -> Vec<u32> {
+        self.offsets.clone()
+    }
 
-    // 3. ループ構築
-    let loops = build_loops(merged_segments);
+    #[wasm_bindgen(getter)]
+    pub fn kinds(&self) -> Vec<u8> {
+        self.kinds.clone()
+    }
 
-    // 4. SVGパス文字列生成
-    let mut path_parts = Vec::new();
+    #[wasm_bindgen(getter)]
+    pub fn loop_count(&self) -> u32 {
+        self.kinds.len() as u32
+    }
+}
 
-    for loop_points in loops {
-        for (i, pt) in loop_points.iter().enumerate() {
-            let x = pt.x as f32 + offset_x;
-            let y = pt.y as f32 + offset_y;
+impl OutlinePolylines {
+    fn from_loops(loops: &[OutlineLoop], offset_x: f32, offset_y: f32) -> Self {
+        Self::build(
+            loops.iter().map(|l| {
+                let points = l.points.iter().map(|p| (p.x as f32, p.y as f32));
+                (l.kind, points)
+            }),
+            offset_x,
+            offset_y,
+        )
+    }
 
-            if i == 0 {
-                path_parts.push(format!("M {} {}", x, y));
-            } else {
-                path_parts.push(format!("L {} {}", x, y));
+    fn from_iso_loops(loops: &[IsoLoop], offset_x: f32, offset_y: f32) -> Self {
+        Self::build(
+            loops.iter().map(|l| {
+                let points = l.points.iter().map(|p| (p.x as f32, p.y as f32));
+                (l.kind, points)
+            }),
+            offset_x,
+            offset_y,
+        )
+    }
+
+    fn build<I, P>(loops: I, offset_x: f32, offset_y: f32) -> Self
+    where
+        I: Iterator<Item = (LoopKind, P)>,
+        P: Iterator<Item = (f32, f32)>,
+    {
+        let mut vertices = Vec::new();
+        let mut offsets = Vec::new();
+        let mut kinds = Vec::new();
+
+        for (kind, points) in loops {
+            offsets.push((vertices.len() / 2) as u32);
+            kinds.push(kind as u8);
+            for (x, y) in points {
+                vertices.push(x + offset_x);
+                vertices.push(y + offset_y);
             }
         }
-        path_parts.push("Z".to_string());
+        offsets.push((vertices.len() / 2) as u32);
+
+        Self {
+            vertices,
+            offsets,
+            kinds,
+        }
     }
+}
+
+// 頂点から出る境界辺の向き（頂点グリッドごとのビットフラグ）
+// 辺は常に「選択ピクセルが進行方向の右側」になる向きで持つ
+// そのため外周は画面上で時計回り、穴は反時計回りになり、
+// nonzero / evenodd どちらの塗りつぶし規則でも穴が反転しない
+// ビットは右回り（右→下→左→上）の順に並べてあり、1ビット左回転が「右折」になる
+const EDGE_RIGHT: u8 = 1 << 0;
+const EDGE_DOWN: u8 = 1 << 1;
+const EDGE_LEFT: u8 = 1 << 2;
+const EDGE_UP: u8 = 1 << 3;
+
+/// 選択範囲マスクからSVGパス文字列を生成
+#[wasm_bindgen]
+pub fn mask_to_path(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    offset_x: f32,
+    offset_y: f32,
+) -> Result<String, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+
+    let loops = trace_mask_outline(mask, width, height);
 
-    path_parts.join(" ")
+    let mut path = String::new();
+    write_outline_loops(&mut path, &loops, offset_x, offset_y);
+    Ok(path)
 }
 
-/// 1. 境界セグメント抽出
-fn extract_boundary_segments(mask: &[u8], w: i32, h: i32) -> Vec<Segment> {
-    use std::collections::HashMap;
-    use std::collections::hash_map::Entry::{Occupied, Vacant};
+/// ループの列をSVGパス (M/L/Z) として書き出す
+pub(crate) fn write_outline_loops(
+    path: &mut String,
+    loops: &[OutlineLoop],
+    offset_x: f32,
+    offset_y: f32,
+) {
+    for outline in loops {
+        let points = outline
+            .points
+            .iter()
+            .map(|pt| (pt.x as f32 + offset_x, pt.y as f32 + offset_y));
+        write_polyline(path, points, true);
+    }
+}
 
-    let mut key_set: HashMap<String, Segment> = HashMap::new();
+// 1本の折れ線を M/L で書き出す（closed なら Z で閉じる）
+fn write_polyline<T: std::fmt::Display>(
+    path: &mut String,
+    points: impl IntoIterator<Item = (T, T)>,
+    closed: bool,
+) {
+    for (i, (x, y)) in points.into_iter().enumerate() {
+        if !path.is_empty() {
+            path.push(' ');
+        }
+        let command = if i == 0 { 'M' } else { 'L' };
+        let _ = write!(path, "{} {} {}", command, x, y);
+    }
+    if closed {
+        path.push_str(" Z");
+    }
+}
 
-    let add_or_remove = |key_set: &mut HashMap<String, Segment>, a: Point, b: Point| {
-        // 頂点をソートしてキーを統一
-        let (p1, p2) = if a.x < b.x || (a.x == b.x && a.y < b.y) {
-            (a, b)
-        } else {
-            (b, a)
-        };
+/// 選択範囲マスクから頂点配列 + ループ境界 + 種別の形でアウトラインを生成
+/// SVG文字列を経由せずに WebGL / Canvas2D で直接描画するためのもの
+#[wasm_bindgen]
+pub fn mask_to_polylines(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    offset_x: f32,
+    offset_y: f32,
+) -> Result<OutlinePolylines, JsError> {
+    check_mask_len("mask", mask, width, height)?;
 
-        let key = format!("{},{}-{},{}", p1.x, p1.y, p2.x, p2.y);
+    let loops = trace_mask_outline(mask, width, height);
+    Ok(OutlinePolylines::from_loops(&loops, offset_x, offset_y))
+}
 
-        match key_set.entry(key) {
-            Occupied(e) => {
-                e.remove(); // 重複なら内部→除去
-            }
-            Vacant(e) => {
-                e.insert(Segment { p1, p2 });
+/// マスクの境界をループの列として抽出する（Rust側から使うためのAPI）
+pub fn trace_mask_outline(mask: &[u8], width: u32, height: u32) -> Vec<OutlineLoop> {
+    let w = width as usize;
+    let h = height as usize;
+    let region = GridRegion {
+        x0: 0,
+        y0: 0,
+        x1: w,
+        y1: h,
+    };
+
+    // 1. 境界辺抽出（(w+1)*(h+1) の頂点グリッド上の有向辺）
+    let mut edges = extract_boundary_edges(mask, w, h, region);
+
+    // 2. 辺を辿ってループ構築（同一直線上の頂点は落とし、角のみ残す）
+    let paths = trace_paths(&mut edges, region);
+
+    // 3. 向きから外周/穴を判定
+    paths
+        .into_iter()
+        .map(|path| {
+            let kind = if signed_area2(&path.points) >= 0 {
+                LoopKind::Outer
+            } else {
+                LoopKind::Hole
+            };
+            OutlineLoop {
+                points: path.points,
+                kind,
             }
+        })
+        .collect()
+}
+
+/// 表示範囲と交わる部分のアウトラインだけをスクリーン座標のSVGパスとして生成
+/// view_x, view_y, view_width, view_height: 表示範囲（キャンバス座標）
+/// zoom: キャンバス1ピクセルあたりのスクリーンピクセル数
+/// 出力座標は ((キャンバス座標 - view) * zoom)。
+/// 表示範囲の外へはみ出すループは閉じず、範囲内の部分だけを開いた折れ線（Zなし）として出力する
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn mask_to_path_in_viewport(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    view_x: f32,
+    view_y: f32,
+    view_width: f32,
+    view_height: f32,
+    zoom: f32,
+) -> Result<String, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+    for (name, value) in [
+        ("view_x", view_x),
+        ("view_y", view_y),
+        ("view_width", view_width),
+        ("view_height", view_height),
+        ("zoom", zoom),
+    ] {
+        check_finite(name, value)?;
+    }
+    if zoom <= 0.0 {
+        return Err(JsError::new("zoom must be greater than 0"));
+    }
+
+    let w = width as usize;
+    let h = height as usize;
+
+    // 表示範囲をピクセル境界に広げ、キャンバス内に制限
+    let clamp = |v: f32, max: usize| -> usize { (v.max(0.0) as usize).min(max) };
+    let region = GridRegion {
+        x0: clamp(view_x.floor(), w),
+        y0: clamp(view_y.floor(), h),
+        x1: clamp((view_x + view_width).ceil(), w),
+        y1: clamp((view_y + view_height).ceil(), h),
+    };
+    if region.x0 >= region.x1 || region.y0 >= region.y1 {
+        return Ok(String::new());
+    }
+
+    let mut edges = extract_boundary_edges(mask, w, h, region);
+    let paths = trace_paths(&mut edges, region);
+
+    let mut path = String::new();
+    for traced in &paths {
+        let points = traced
+            .points
+            .iter()
+            .map(|pt| ((pt.x as f32 - view_x) * zoom, (pt.y as f32 - view_y) * zoom));
+        write_polyline(&mut path, points, traced.closed);
+    }
+
+    Ok(path)
+}
+
+// 頂点グリッド上の矩形範囲（x0..=x1, y0..=y1 の頂点を含む）
+#[derive(Clone, Copy, Debug)]
+struct GridRegion {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+impl GridRegion {
+    fn stride(&self) -> usize {
+        self.x1 - self.x0 + 1
+    }
+
+    fn vertex_count(&self) -> usize {
+        self.stride() * (self.y1 - self.y0 + 1)
+    }
+
+    fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x0 && x <= self.x1 && y >= self.y0 && y <= self.y1
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y - self.y0) * self.stride() + (x - self.x0)
+    }
+
+    fn point(&self, index: usize) -> Point {
+        Point {
+            x: (self.x0 + index % self.stride()) as i32,
+            y: (self.y0 + index / self.stride()) as i32,
         }
+    }
+}
+
+// 辿った境界（閉じたループ、または範囲の端で途切れた折れ線）
+struct TracedPath {
+    points: Vec<Point>,
+    closed: bool,
+}
+
+/// 1. 境界辺抽出
+///
+/// 戻り値は範囲内の頂点ごとの出辺ビット（index は GridRegion::index）。
+/// 両端が範囲内にある辺だけを持つ。範囲の境界線上の辺のために1ピクセル外側まで見る
+fn extract_boundary_edges(mask: &[u8], w: usize, h: usize, region: GridRegion) -> Vec<u8> {
+    let mut edges = vec![0u8; region.vertex_count()];
+
+    let is_set = |x: usize, y: usize| -> bool {
+        let idx = y * w + x;
+        idx < mask.len() && mask[idx] != 0
     };
 
-    for y in 0..h {
-        for x in 0..w {
-            let idx = (y * w + x) as usize;
-            if idx >= mask.len() || mask[idx] == 0 {
+    let mut add_edge = |x: usize, y: usize, dir: u8, tx: usize, ty: usize| {
+        if region.contains(x, y) && region.contains(tx, ty) {
+            edges[region.index(x, y)] |= dir;
+        }
+    };
+
+    for y in region.y0.saturating_sub(1)..(region.y1 + 1).min(h) {
+        for x in region.x0.saturating_sub(1)..(region.x1 + 1).min(w) {
+            if !is_set(x, y) {
                 continue;
             }
 
-            // 上辺
-            if y == 0 || mask[((y - 1) * w + x) as usize] == 0 {
-                add_or_remove(&mut key_set, Point { x, y }, Point { x: x + 1, y });
+            // 上辺（左→右）
+            if y == 0 || !is_set(x, y - 1) {
+                add_edge(x, y, EDGE_RIGHT, x + 1, y);
             }
-            // 下辺
-            if y == h - 1 || mask[((y + 1) * w + x) as usize] == 0 {
-                add_or_remove(
-                    &mut key_set,
-                    Point { x: x + 1, y: y + 1 },
-                    Point { x, y: y + 1 },
-                );
+            // 右辺（上→下）
+            if x == w - 1 || !is_set(x + 1, y) {
+                add_edge(x + 1, y, EDGE_DOWN, x + 1, y + 1);
             }
-            // 左辺
-            if x == 0 || mask[(y * w + x - 1) as usize] == 0 {
-                add_or_remove(&mut key_set, Point { x, y: y + 1 }, Point { x, y });
+            // 下辺（右→左）
+            if y == h - 1 || !is_set(x, y + 1) {
+                add_edge(x + 1, y + 1, EDGE_LEFT, x, y + 1);
             }
-            // 右辺
-            if x == w - 1 || mask[(y * w + x + 1) as usize] == 0 {
-                add_or_remove(
-                    &mut key_set,
-                    Point { x: x + 1, y },
-                    Point { x: x + 1, y: y + 1 },
-                );
+            // 左辺（下→上）
+            if x == 0 || !is_set(x - 1, y) {
+                add_edge(x, y + 1, EDGE_UP, x, y);
             }
         }
     }
 
-    key_set.into_values().collect()
+    edges
 }
 
-/// 2. セグメントマージ
-fn merge_segments(segs: Vec<Segment>) -> Vec<Segment> {
-    let mut horiz = Vec::new();
-    let mut vert = Vec::new();
+/// 2. ループ構築
+///
+/// 使用した辺はビットを落としていくので、各辺はちょうど一度だけ辿られる
+///
+/// 斜めにだけ接する2ピクセルの角（出辺が2本ある頂点）では常に右折する。
+/// つまり選択ピクセルは4連結で1つの領域とみなし、角だけで接するピクセル同士は
+/// 別のループになる（非選択側から見ると8連結）。
+/// 走査順で最初に見つかる頂点はループの左上の角なので、始点がこの分岐点になることはない。
+///
+/// 範囲で切り取られた辺集合では、出辺が入辺より多い頂点（範囲の端から入ってくる所）から
+/// 先に開いた折れ線として辿り、残った辺を閉じたループとして辿る
+fn trace_paths(edges: &mut [u8], region: GridRegion) -> Vec<TracedPath> {
+    let stride = region.stride();
+    let mut paths = Vec::new();
 
-    // 水平・垂直に分類
-    for s in segs {
-        if s.p1.y == s.p2.y {
-            horiz.push(order_horizontal(s));
-        } else {
-            vert.push(order_vertical(s));
+    // 入辺の数（範囲で切り取られていなければ常に出辺の数と等しい）
+    let in_degree = |edges: &[u8], v: usize| -> u32 {
+        let col = v % stride;
+        let mut count = 0;
+        if col > 0 && edges[v - 1] & EDGE_RIGHT != 0 {
+            count += 1;
+        }
+        if col + 1 < stride && edges[v + 1] & EDGE_LEFT != 0 {
+            count += 1;
+        }
+        if v >= stride && edges[v - stride] & EDGE_DOWN != 0 {
+            count += 1;
+        }
+        if v + stride < edges.len() && edges[v + stride] & EDGE_UP != 0 {
+            count += 1;
+        }
+        count
+    };
+
+    // 開いた折れ線（範囲の端からの始点は範囲の外周上にしかない）
+    for start in 0..edges.len() {
+        let pt = region.point(start);
+        let (x, y) = (pt.x as usize, pt.y as usize);
+        if x != region.x0 && x != region.x1 && y != region.y0 && y != region.y1 {
+            continue;
+        }
+        while edges[start].count_ones() > in_degree(edges, start) {
+            paths.push(trace_path(edges, region, start));
         }
     }
 
-    let mut result = Vec::new();
+    // 閉じたループ（同じ頂点から複数のループが出ることがあるので空になるまで繰り返す）
+    for start in 0..edges.len() {
+        while edges[start] != 0 {
+            paths.push(trace_path(edges, region, start));
+        }
+    }
+
+    paths
+}
+
+/// start から出辺が尽きるか start に戻るまで辿る
+fn trace_path(edges: &mut [u8], region: GridRegion, start: usize) -> TracedPath {
+    let stride = region.stride();
+    let mut points = Vec::new();
+    let mut cur = start;
+    let mut first_dir = 0u8;
+    let mut prev_dir = 0u8;
+
+    loop {
+        let bits = edges[cur];
+        if bits == 0 {
+            break;
+        }
+
+        let dir = pick_direction(bits, prev_dir);
+        edges[cur] &= !dir;
+
+        // 向きが変わる頂点だけを角として記録
+        if dir != prev_dir {
+            points.push(region.point(cur));
+        }
+        if first_dir == 0 {
+            first_dir = dir;
+        }
+        prev_dir = dir;
 
-    // 水平線をグループ化してマージ
-    let mut horiz_groups: std::collections::HashMap<i32, Vec<Segment>> =
-        std::collections::HashMap::new();
-    for seg in horiz {
-        horiz_groups.entry(seg.p1.y).or_default().push(seg);
+        cur = match dir {
+            EDGE_RIGHT => cur + 1,
+            EDGE_DOWN => cur + stride,
+            EDGE_LEFT => cur - 1,
+            _ => cur - stride,
+        };
+
+        if cur == start {
+            break;
+        }
     }
 
-    for mut group in horiz_groups.into_values() {
-        result.extend(merge_line(&mut group, true));
+    let closed = cur == start;
+    if closed {
+        // 始点が直線の途中だった場合は角ではないので除く
+        if first_dir == prev_dir && points.len() > 1 {
+            points.remove(0);
+        }
+    } else {
+        // 途切れた終点
+        points.push(region.point(cur));
     }
 
-    // 垂直線をグループ化してマージ
-    let mut vert_groups: std::collections::HashMap<i32, Vec<Segment>> =
-        std::collections::HashMap::new();
-    for seg in vert {
-        vert_groups.entry(seg.p1.x).or_default().push(seg);
+    TracedPath { points, closed }
+}
+
+/// 頂点の出辺ビットから次に進む向きを1つ選ぶ（右折 > 直進 > 左折 の優先順）
+#[inline]
+fn pick_direction(bits: u8, prev_dir: u8) -> u8 {
+    if prev_dir != 0 {
+        let right = turn_right(prev_dir);
+        for dir in [right, prev_dir, turn_right(turn_right(right))] {
+            if bits & dir != 0 {
+                return dir;
+            }
+        }
     }
+    bits & bits.wrapping_neg()
+}
+
+#[inline]
+fn turn_right(dir: u8) -> u8 {
+    ((dir << 1) | (dir >> 3)) & 0b1111
+}
 
-    for mut group in vert_groups.into_values() {
-        result.extend(merge_line(&mut group, false));
+/// ループの符号付き面積の2倍（y軸下向きの座標系で時計回りが正）
+fn signed_area2(points: &[Point]) -> i64 {
+    let n = points.len();
+    let mut sum = 0i64;
+    for i in 0..n {
+        let a = points[i];
+        let b = points[(i + 1) % n];
+        sum += a.x as i64 * b.y as i64 - b.x as i64 * a.y as i64;
     }
+    sum
+}
 
-    result
+// ---------------------------------------------------------------------------
+// 曲線トレース（potrace方式）
+// 1. 角の列を1ピクセル単位の経路に展開
+// 2. 経路を直線で近似した多角形を作る
+// 3. 多角形の各頂点を角または3次ベジェ曲線に置き換える
+// 4. 連続する曲線を許容誤差内で1本にまとめる
+// ---------------------------------------------------------------------------
+
+// 浮動小数点の座標型
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointF {
+    pub x: f64,
+    pub y: f64,
 }
 
-fn order_horizontal(s: Segment) -> Segment {
-    if s.p1.x <= s.p2.x {
-        s
-    } else {
-        Segment { p1: s.p2, p2: s.p1 }
+impl PointF {
+    fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    fn sub(self, other: PointF) -> PointF {
+        PointF::new(self.x - other.x, self.y - other.y)
+    }
+
+    fn add(self, other: PointF) -> PointF {
+        PointF::new(self.x + other.x, self.y + other.y)
+    }
+
+    fn scale(self, s: f64) -> PointF {
+        PointF::new(self.x * s, self.y * s)
+    }
+
+    // a + t * (b - a)
+    fn lerp(a: PointF, b: PointF, t: f64) -> PointF {
+        a.add(b.sub(a).scale(t))
     }
 }
 
-fn order_vertical(s: Segment) -> Segment {
-    if s.p1.y <= s.p2.y {
-        s
-    } else {
-        Segment { p1: s.p2, p2: s.p1 }
+impl From<Point> for PointF {
+    fn from(p: Point) -> Self {
+        PointF::new(p.x as f64, p.y as f64)
     }
 }
 
-fn merge_line(list: &mut [Segment], is_horizontal: bool) -> Vec<Segment> {
-    if list.is_empty() {
-        return Vec::new();
+/// 曲線アウトラインの1区間（前の区間の終点から始まる）
+#[derive(Clone, Copy, Debug)]
+pub enum CurveSegment {
+    /// 角: vertex まで直線、そこから end まで直線
+    Corner { vertex: PointF, end: PointF },
+    /// 3次ベジェ曲線
+    Bezier { c1: PointF, c2: PointF, end: PointF },
+}
+
+/// 1本の閉じた曲線アウトライン
+#[derive(Clone, Debug)]
+pub struct CurveLoop {
+    pub start: PointF,
+    pub segments: Vec<CurveSegment>,
+    pub kind: LoopKind,
+}
+
+/// 選択範囲マスクから直線と3次ベジェ曲線で近似したSVGパス文字列を生成（potrace方式）
+/// alpha_max: 角とみなすしきい値（potraceの alphamax。既定は 1.0、0 で全て角、4/3 以上で角なし）
+/// opt_tolerance: 連続する曲線をまとめるときの許容誤差（ピクセル単位。既定は 0.2、0 以下でまとめない）
+#[wasm_bindgen]
+pub fn mask_to_smooth_path(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    offset_x: f32,
+    offset_y: f32,
+    alpha_max: f32,
+    opt_tolerance: f32,
+) -> Result<String, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+    check_finite("alpha_max", alpha_max)?;
+    check_finite("opt_tolerance", opt_tolerance)?;
+
+    let loops = trace_mask_curves(mask, width, height, alpha_max as f64, opt_tolerance as f64);
+    let offset = PointF::new(offset_x as f64, offset_y as f64);
+
+    let mut path = String::new();
+    for curve in &loops {
+        write_curve_loop(&mut path, curve, offset);
     }
+    Ok(path)
+}
 
-    // ソート
-    if is_horizontal {
-        list.sort_by(|a, b| a.p1.x.cmp(&b.p1.x));
-    } else {
-        list.sort_by(|a, b| a.p1.y.cmp(&b.p1.y));
+/// マスクの境界を曲線ループの列として抽出する（Rust側から使うためのAPI）
+pub fn trace_mask_curves(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    alpha_max: f64,
+    opt_tolerance: f64,
+) -> Vec<CurveLoop> {
+    trace_mask_outline(mask, width, height)
+        .iter()
+        .map(|outline| trace_curve_loop(outline, alpha_max, opt_tolerance))
+        .collect()
+}
+
+/// 曲線ループをSVGパスとして書き出す（座標は (p + offset)）
+fn write_curve_loop(path: &mut String, curve: &CurveLoop, offset: PointF) {
+    let tr = |p: PointF| p.add(offset);
+
+    if !path.is_empty() {
+        path.push(' ');
+    }
+    let start = tr(curve.start);
+    let _ = write!(path, "M {} {}", fmt_coord(start.x), fmt_coord(start.y));
+
+    for segment in &curve.segments {
+        match *segment {
+            CurveSegment::Corner { vertex, end } => {
+                let (v, e) = (tr(vertex), tr(end));
+                let _ = write!(
+                    path,
+                    " L {} {} L {} {}",
+                    fmt_coord(v.x),
+                    fmt_coord(v.y),
+                    fmt_coord(e.x),
+                    fmt_coord(e.y)
+                );
+            }
+            CurveSegment::Bezier { c1, c2, end } => {
+                let (c1, c2, e) = (tr(c1), tr(c2), tr(end));
+                let _ = write!(
+                    path,
+                    " C {} {} {} {} {} {}",
+                    fmt_coord(c1.x),
+                    fmt_coord(c1.y),
+                    fmt_coord(c2.x),
+                    fmt_coord(c2.y),
+                    fmt_coord(e.x),
+                    fmt_coord(e.y)
+                );
+            }
+        }
     }
+    path.push_str(" Z");
+}
 
-    let mut result = Vec::new();
-    let mut current = list[0].clone();
+// 小数点以下3桁に丸めて出力（末尾の0は付かない）
+fn fmt_coord(v: f64) -> f64 {
+    let r = (v * 1000.0).round() / 1000.0;
+    if r == 0.0 { 0.0 } else { r }
+}
 
-    for seg in list.iter().skip(1) {
-        let can_merge = if is_horizontal {
-            current.p2.x >= seg.p1.x
-        } else {
-            current.p2.y >= seg.p1.y
+fn trace_curve_loop(outline: &OutlineLoop, alpha_max: f64, opt_tolerance: f64) -> CurveLoop {
+    // 1. 1ピクセル単位の経路に展開
+    let path = expand_unit_path(&outline.points);
+
+    // 2. 直線近似の多角形（頂点が3つ未満になる小さな形は角の列をそのまま使う）
+    let mut polygon = best_polygon(&path);
+    if polygon.len() < 3 {
+        polygon = outline.points.iter().map(|&p| PointF::from(p)).collect();
+    }
+
+    // 3. 角/曲線への置き換え
+    let mut segments = smooth_polygon(&polygon, alpha_max);
+
+    // 4. 曲線の結合
+    if opt_tolerance > 0.0 {
+        segments = optimize_curves(segments, opt_tolerance);
+    }
+
+    let m = polygon.len();
+    CurveLoop {
+        start: PointF::lerp(polygon[m - 1], polygon[0], 0.5),
+        segments: segments.into_iter().map(|s| s.segment).collect(),
+        kind: outline.kind,
+    }
+}
+
+/// 1. 角の列を1ピクセル単位の経路に展開
+fn expand_unit_path(corners: &[Point]) -> Vec<Point> {
+    let n = corners.len();
+    let mut path = Vec::new();
+
+    for i in 0..n {
+        let a = corners[i];
+        let b = corners[(i + 1) % n];
+        let dx = (b.x - a.x).signum();
+        let dy = (b.y - a.y).signum();
+
+        let mut p = a;
+        while p != b {
+            path.push(p);
+            p = Point {
+                x: p.x + dx,
+                y: p.y + dy,
+            };
+        }
+    }
+
+    path
+}
+
+/// 2. 直線近似の多角形
+///
+/// 始点から、途中の全頂点を中心とする1x1の正方形を通れる限り直線を伸ばす（potraceの直線判定）。
+/// 伸ばせるところまで進んで次の頂点とする貪欲法で、経路の長さに対してほぼ線形
+fn best_polygon(path: &[Point]) -> Vec<PointF> {
+    let n = path.len();
+    let mut polygon = Vec::new();
+
+    let mut i = 0;
+    while i < n {
+        polygon.push(PointF::from(path[i]));
+        i += longest_straight(path, i, n - i);
+    }
+
+    polygon
+}
+
+/// path[start] から直線で到達できる最大ステップ数（1..=max_len）
+fn longest_straight(path: &[Point], start: usize, max_len: usize) -> usize {
+    let n = path.len();
+    let s = PointF::from(path[start]);
+
+    let mut directions = 0u8;
+    let mut cone: Option<(PointF, PointF)> = None;
+    let mut best = 1;
+
+    for len in 1..=max_len {
+        let prev = path[(start + len - 1) % n];
+        let q = path[(start + len) % n];
+
+        // 4方向すべてを含む経路は直線ではない
+        directions |= match (q.x - prev.x, q.y - prev.y) {
+            (1, _) => EDGE_RIGHT,
+            (-1, _) => EDGE_LEFT,
+            (_, 1) => EDGE_DOWN,
+            _ => EDGE_UP,
         };
+        if directions == 0b1111 {
+            break;
+        }
 
-        if can_merge {
-            // マージ
-            if is_horizontal {
-                current.p2.x = current.p2.x.max(seg.p2.x);
-            } else {
-                current.p2.y = current.p2.y.max(seg.p2.y);
+        // 終点への向きがこれまでの制約（角度範囲）に収まっているか
+        let v = PointF::from(q).sub(s);
+        if let Some((lo, hi)) = cone
+            && (cross(lo, v) < 0.0 || cross(v, hi) < 0.0)
+        {
+            break;
+        }
+        best = len;
+
+        // q を中心とする正方形を通るという制約を追加
+        let (lo, hi) = square_cone(v);
+        cone = match cone {
+            None => Some((lo, hi)),
+            Some((a, b)) => {
+                let lo = if cross(a, lo) >= 0.0 { lo } else { a };
+                let hi = if cross(hi, b) >= 0.0 { hi } else { b };
+                if cross(lo, hi) < 0.0 {
+                    break;
+                }
+                Some((lo, hi))
             }
+        };
+    }
+
+    best
+}
+
+/// 原点から見て v を中心とする1x1の正方形が占める角度範囲（右回りの端, 左回りの端）
+fn square_cone(v: PointF) -> (PointF, PointF) {
+    let corners = [
+        PointF::new(v.x - 0.5, v.y - 0.5),
+        PointF::new(v.x + 0.5, v.y - 0.5),
+        PointF::new(v.x + 0.5, v.y + 0.5),
+        PointF::new(v.x - 0.5, v.y + 0.5),
+    ];
+
+    let mut lo = corners[0];
+    let mut hi = corners[0];
+    for &c in &corners[1..] {
+        if cross(c, lo) > 0.0 {
+            lo = c;
+        }
+        if cross(hi, c) > 0.0 {
+            hi = c;
+        }
+    }
+    (lo, hi)
+}
+
+#[inline]
+fn cross(a: PointF, b: PointF) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+// 最適化のために頂点情報を保持した区間
+#[derive(Clone, Copy, Debug)]
+struct SmoothSegment {
+    start: PointF,
+    segment: CurveSegment,
+    // 元の多角形での曲がる向き（符号のみ使用）と曲がる角度
+    turn: f64,
+    angle: f64,
+}
+
+impl SmoothSegment {
+    fn end(&self) -> PointF {
+        match self.segment {
+            CurveSegment::Corner { end, .. } => end,
+            CurveSegment::Bezier { end, .. } => end,
+        }
+    }
+}
+
+/// 3. 多角形の各頂点を角または曲線に置き換える
+///
+/// 頂点 j は辺 (j-1, j) の中点から辺 (j, j+1) の中点までの区間になる
+fn smooth_polygon(polygon: &[PointF], alpha_max: f64) -> Vec<SmoothSegment> {
+    let m = polygon.len();
+    let mut segments = Vec::with_capacity(m);
+
+    for j in 0..m {
+        let vi = polygon[(j + m - 1) % m];
+        let vj = polygon[j];
+        let vk = polygon[(j + 1) % m];
+
+        let start = PointF::lerp(vi, vj, 0.5);
+        let end = PointF::lerp(vj, vk, 0.5);
+
+        // 頂点の鋭さ。vi-vk を結ぶ直線から vj までの距離を正規化したもの
+        let denom = ddenom(vi, vk);
+        let alpha = if denom != 0.0 {
+            let dd = (cross(vj.sub(vi), vk.sub(vi)) / denom).abs();
+            let a = if dd > 1.0 { 1.0 - 1.0 / dd } else { 0.0 };
+            a / 0.75
         } else {
-            result.push(current);
-            current = seg.clone();
+            4.0 / 3.0
+        };
+
+        let segment = if alpha >= alpha_max {
+            CurveSegment::Corner { vertex: vj, end }
+        } else {
+            let a = alpha.clamp(0.55, 1.0);
+            CurveSegment::Bezier {
+                c1: PointF::lerp(vi, vj, 0.5 + 0.5 * a),
+                c2: PointF::lerp(vk, vj, 0.5 + 0.5 * a),
+                end,
+            }
+        };
+
+        let (e0, e1) = (vj.sub(vi), vk.sub(vj));
+        segments.push(SmoothSegment {
+            start,
+            segment,
+            turn: cross(e0, e1),
+            angle: cross(e0, e1).atan2(e0.x * e1.x + e0.y * e1.y).abs(),
+        });
+    }
+
+    segments
+}
+
+// potraceの ddenom: p0-p2 方向に直交する軸（無限大ノルム）での正規化係数
+fn ddenom(p0: PointF, p2: PointF) -> f64 {
+    let dx = p2.x - p0.x;
+    let dy = p2.y - p0.y;
+    let rx = -sign(dy);
+    let ry = sign(dx);
+    ry * dx - rx * dy
+}
+
+#[inline]
+fn sign(v: f64) -> f64 {
+    if v > 0.0 {
+        1.0
+    } else if v < 0.0 {
+        -1.0
+    } else {
+        0.0
+    }
+}
+
+/// 4. 同じ向きに曲がり続ける曲線の列を、許容誤差内なら1本の曲線にまとめる
+fn optimize_curves(segments: Vec<SmoothSegment>, tolerance: f64) -> Vec<SmoothSegment> {
+    let mut result = Vec::with_capacity(segments.len());
+
+    let mut i = 0;
+    while i < segments.len() {
+        let mut merged = segments[i];
+        let mut j = i + 1;
+
+        if matches!(segments[i].segment, CurveSegment::Bezier { .. }) {
+            let mut total_angle = segments[i].angle;
+            while j < segments.len() {
+                let next = &segments[j];
+                total_angle += next.angle;
+                if !matches!(next.segment, CurveSegment::Bezier { .. })
+                    || next.turn * segments[i].turn <= 0.0
+                    || total_angle >= std::f64::consts::PI * 0.99
+                {
+                    break;
+                }
+                match fit_merged_curve(&segments[i..=j], tolerance) {
+                    Some(segment) => merged = segment,
+                    None => break,
+                }
+                j += 1;
+            }
         }
+
+        result.push(merged);
+        i = j;
     }
 
-    result.push(current);
     result
 }
 
-/// 3. ループ構築
-fn build_loops(segs: Vec<Segment>) -> Vec<Vec<Point>> {
-    use std::collections::{HashMap, HashSet};
+/// 区間の列を1本のベジェ曲線で近似する。両端の接線方向は元の曲線に合わせる
+fn fit_merged_curve(run: &[SmoothSegment], tolerance: f64) -> Option<SmoothSegment> {
+    let first = run.first()?;
+    let last = run.last()?;
+    let (CurveSegment::Bezier { c1: t0, .. }, CurveSegment::Bezier { c2: t1, .. }) =
+        (first.segment, last.segment)
+    else {
+        return None;
+    };
+
+    let p0 = first.start;
+    let p3 = last.end();
+    let d0 = t0.sub(p0);
+    let d1 = t1.sub(p3);
 
-    let mut used = HashSet::new();
-    let mut idx_by_pt: HashMap<String, Vec<Segment>> = HashMap::new();
+    // 両端の接線の交点
+    let denom = cross(d0, d1);
+    if denom.abs() < 1e-9 {
+        return None;
+    }
+    let s = cross(p3.sub(p0), d1) / denom;
+    let u = cross(p3.sub(p0), d0) / denom;
+    if s <= 0.0 || u <= 0.0 {
+        return None;
+    }
+    let corner = p0.add(d0.scale(s));
 
-    // 頂点→セグメント索引の逆引き
-    for seg in &segs {
-        for pt in [seg.p1, seg.p2] {
-            let key = format!("{},{}", pt.x, pt.y);
-            idx_by_pt.entry(key).or_default().push(seg.clone());
+    // 元の曲線上の点
+    let mut samples = Vec::new();
+    for seg in run {
+        if let CurveSegment::Bezier { c1, c2, end } = seg.segment {
+            for t in [0.25, 0.5, 0.75, 1.0] {
+                samples.push(bezier_point(seg.start, c1, c2, end, t));
+            }
         }
     }
 
-    let mut loops = Vec::new();
+    // 制御点の位置（交点までの比率）を黄金分割探索で決める
+    let build = |alpha: f64| {
+        (
+            PointF::lerp(p0, corner, alpha),
+            PointF::lerp(p3, corner, alpha),
+        )
+    };
+    let error = |alpha: f64| {
+        let (c1, c2) = build(alpha);
+        max_deviation(&samples, p0, c1, c2, p3)
+    };
 
-    for s0 in &segs {
-        let id0 = seg_key(s0.p1, s0.p2);
-        if used.contains(&id0) {
-            continue;
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    let (mut lo, mut hi) = (0.3, 1.3);
+    let mut a = hi - ratio * (hi - lo);
+    let mut b = lo + ratio * (hi - lo);
+    let (mut ea, mut eb) = (error(a), error(b));
+    for _ in 0..24 {
+        if ea < eb {
+            hi = b;
+            b = a;
+            eb = ea;
+            a = hi - ratio * (hi - lo);
+            ea = error(a);
+        } else {
+            lo = a;
+            a = b;
+            ea = eb;
+            b = lo + ratio * (hi - lo);
+            eb = error(b);
         }
+    }
+    let alpha = (lo + hi) / 2.0;
+    if error(alpha) > tolerance {
+        return None;
+    }
+
+    let (c1, c2) = build(alpha);
+    Some(SmoothSegment {
+        start: p0,
+        segment: CurveSegment::Bezier { c1, c2, end: p3 },
+        turn: first.turn,
+        angle: run.iter().map(|s| s.angle).sum(),
+    })
+}
 
-        let mut loop_points = vec![s0.p1, s0.p2];
-        used.insert(id0);
+fn bezier_point(p0: PointF, c1: PointF, c2: PointF, p3: PointF, t: f64) -> PointF {
+    let mt = 1.0 - t;
+    p0.scale(mt * mt * mt)
+        .add(c1.scale(3.0 * mt * mt * t))
+        .add(c2.scale(3.0 * mt * t * t))
+        .add(p3.scale(t * t * t))
+}
 
-        let mut prev = s0.p1;
-        let mut cur = s0.p2;
+// 各サンプル点から曲線（折れ線で近似）までの距離の最大値
+fn max_deviation(samples: &[PointF], p0: PointF, c1: PointF, c2: PointF, p3: PointF) -> f64 {
+    const STEPS: usize = 32;
+    let polyline: Vec<PointF> = (0..=STEPS)
+        .map(|i| bezier_point(p0, c1, c2, p3, i as f64 / STEPS as f64))
+        .collect();
 
-        // 次々とつながるセグメントを追跡
-        loop {
-            let cur_key = format!("{},{}", cur.x, cur.y);
-            let empty_vec = Vec::new();
-            let candidates = idx_by_pt.get(&cur_key).unwrap_or(&empty_vec);
+    samples
+        .iter()
+        .map(|&p| {
+            polyline
+                .windows(2)
+                .map(|w| distance_to_segment(p, w[0], w[1]))
+                .fold(f64::INFINITY, f64::min)
+        })
+        .fold(0.0, f64::max)
+}
 
-            let next = candidates.iter().find(|s| {
-                let k = seg_key(s.p1, s.p2);
-                !used.contains(&k) && !points_equal(s, prev)
-            });
+fn distance_to_segment(p: PointF, a: PointF, b: PointF) -> f64 {
+    let ab = b.sub(a);
+    let len2 = ab.x * ab.x + ab.y * ab.y;
+    let t = if len2 > 0.0 {
+        ((p.x - a.x) * ab.x + (p.y - a.y) * ab.y) / len2
+    } else {
+        0.0
+    };
+    let q = PointF::lerp(a, b, t.clamp(0.0, 1.0));
+    let d = p.sub(q);
+    (d.x * d.x + d.y * d.y).sqrt()
+}
 
-            if let Some(next) = next {
-                let next_key = seg_key(next.p1, next.p2);
-                used.insert(next_key);
+// ---------------------------------------------------------------------------
+// 等値線（marching squares）
+// 8bitのソフトマスクをピクセル中心でサンプリングし、しきい値の等値線をサブピクセル精度で求める
+// ---------------------------------------------------------------------------
 
-                let next_pt = if next.p1.x == cur.x && next.p1.y == cur.y {
-                    next.p2
-                } else {
-                    next.p1
-                };
+/// 1本の閉じた等値線
+#[derive(Clone, Debug)]
+pub struct IsoLoop {
+    pub points: Vec<PointF>,
+    pub kind: LoopKind,
+}
 
-                loop_points.push(next_pt);
-                prev = cur;
-                cur = next_pt;
+/// ソフトマスク（0-255 の被覆率）の等値線をSVGパス文字列として生成
+/// 値はそのまま被覆率として読むので、0/1 の2値マスクには mask_to_path を使う
+/// threshold: この値以上を選択範囲内とみなす（省略時は 128 = 50%、0 は 1 として扱う）
+#[wasm_bindgen]
+pub fn soft_mask_to_path(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    threshold: Option<u8>,
+    offset_x: f32,
+    offset_y: f32,
+) -> Result<String, JsError> {
+    check_mask_len("mask", mask, width, height)?;
 
-                // ループ完成チェック
-                if cur.x == loop_points[0].x && cur.y == loop_points[0].y {
-                    break;
+    let loops = trace_soft_mask_isolines(mask, width, height, threshold.unwrap_or(128));
+
+    let mut path = String::new();
+    for iso in &loops {
+        let points = iso.points.iter().map(|pt| {
+            (
+                fmt_coord(pt.x + offset_x as f64),
+                fmt_coord(pt.y + offset_y as f64),
+            )
+        });
+        write_polyline(&mut path, points, true);
+    }
+    Ok(path)
+}
+
+/// ソフトマスク（0-255）の等値線を mask_to_polylines と同じ形式で生成
+#[wasm_bindgen]
+pub fn soft_mask_to_polylines(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    threshold: Option<u8>,
+    offset_x: f32,
+    offset_y: f32,
+) -> Result<OutlinePolylines, JsError> {
+    check_mask_len("mask", mask, width, height)?;
+
+    let loops = trace_soft_mask_isolines(mask, width, height, threshold.unwrap_or(128));
+    Ok(OutlinePolylines::from_iso_loops(&loops, offset_x, offset_y))
+}
+
+/// ソフトマスクの等値線をループの列として抽出する（Rust側から使うためのAPI）
+///
+/// 値はピクセル中心 (x + 0.5, y + 0.5) のサンプルとして扱い、キャンバスの外側は 0 とみなすので
+/// ループは必ず閉じる。向きは mask_to_path と同じく外周が時計回り、穴が反時計回り。
+/// 対角だけが内側になるセル（鞍点）はセル中心の平均値で判定し、平均がしきい値以上なら内側をつなぐ。
+/// threshold が 0 だとキャンバスの外側まで内側になって境界がなくなるので、1 として扱う
+pub fn trace_soft_mask_isolines(
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    threshold: u8,
+) -> Vec<IsoLoop> {
+    const NONE: u32 = u32::MAX;
+
+    let threshold = threshold.max(1);
+
+    let w = width as usize;
+    let h = height as usize;
+    // 外周に 0 を1周足したサンプルグリッド
+    let sw = w + 2;
+    let sh = h + 2;
+    let level = threshold as f64;
+
+    let sample = |sx: usize, sy: usize| -> u8 {
+        if sx == 0 || sy == 0 || sx > w || sy > h {
+            return 0;
+        }
+        let idx = (sy - 1) * w + (sx - 1);
+        if idx < mask.len() { mask[idx] } else { 0 }
+    };
+
+    // 交点ID: 横辺 (sx,sy)-(sx+1,sy) は sy*sw+sx、縦辺 (sx,sy)-(sx,sy+1) は sw*sh + sy*sw+sx
+    let vertical_base = sw * sh;
+    let mut next = vec![NONE; 2 * sw * sh];
+
+    for sy in 0..sh - 1 {
+        for sx in 0..sw - 1 {
+            let corners = [
+                sample(sx, sy),
+                sample(sx + 1, sy),
+                sample(sx + 1, sy + 1),
+                sample(sx, sy + 1),
+            ];
+            let inside = corners.map(|v| v >= threshold);
+            if inside.iter().all(|&b| b) || inside.iter().all(|&b| !b) {
+                continue;
+            }
+
+            // セルの辺を時計回り（上, 右, 下, 左）に並べたときの交点ID
+            let edge_ids = [
+                sy * sw + sx,
+                vertical_base + sy * sw + sx + 1,
+                (sy + 1) * sw + sx,
+                vertical_base + sy * sw + sx,
+            ];
+
+            // 鞍点でセル中心が内側なら内側同士をつなぐ
+            let saddle = inside[0] == inside[2] && inside[1] == inside[3];
+            let center_inside =
+                corners.iter().map(|&v| v as u32).sum::<u32>() as f64 / 4.0 >= level;
+            let skip = if saddle && !center_inside { 1 } else { 0 };
+
+            // 時計回りに「内→外」の辺から次の「外→内」の辺へ向かう線分を張る
+            // （内側が進行方向の右側になる）
+            for e in 0..4 {
+                if !inside[e] || inside[(e + 1) % 4] {
+                    continue;
+                }
+                let mut found = 0;
+                for k in 1..4 {
+                    let f = (e + k) % 4;
+                    if !inside[f] && inside[(f + 1) % 4] {
+                        if found == skip {
+                            next[edge_ids[e]] = edge_ids[f] as u32;
+                            break;
+                        }
+                        found += 1;
+                    }
                 }
-            } else {
-                break;
             }
         }
+    }
 
-        loops.push(loop_points);
+    // 交点の座標（キャンバス座標。サンプル (sx, sy) はピクセル中心 (sx - 0.5, sy - 0.5)）
+    let position = |id: usize| -> PointF {
+        let (local, horizontal) = if id < vertical_base {
+            (id, true)
+        } else {
+            (id - vertical_base, false)
+        };
+        let (sx, sy) = (local % sw, local / sw);
+        let (tx, ty) = if horizontal {
+            (sx + 1, sy)
+        } else {
+            (sx, sy + 1)
+        };
+
+        let a = sample(sx, sy) as f64;
+        let b = sample(tx, ty) as f64;
+        let t = if a != b {
+            ((level - a) / (b - a)).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+
+        let base = PointF::new(sx as f64 - 0.5, sy as f64 - 0.5);
+        if horizontal {
+            PointF::new(base.x + t, base.y)
+        } else {
+            PointF::new(base.x, base.y + t)
+        }
+    };
+
+    let mut loops = Vec::new();
+    for start in 0..next.len() {
+        if next[start] == NONE {
+            continue;
+        }
+
+        let mut points = Vec::new();
+        let mut cur = start;
+        while next[cur] != NONE {
+            points.push(position(cur));
+            let n = next[cur] as usize;
+            next[cur] = NONE;
+            cur = n;
+        }
+
+        let points = remove_collinear(points);
+        if points.len() < 3 {
+            continue;
+        }
+        let kind = if signed_area_f(&points) >= 0.0 {
+            LoopKind::Outer
+        } else {
+            LoopKind::Hole
+        };
+        loops.push(IsoLoop { points, kind });
     }
 
     loops
 }
 
-fn seg_key(a: Point, b: Point) -> String {
-    format!("{},{}-{},{}", a.x, a.y, b.x, b.y)
+// 同一直線上の頂点を取り除く
+fn remove_collinear(points: Vec<PointF>) -> Vec<PointF> {
+    let n = points.len();
+    if n < 3 {
+        return points;
+    }
+    (0..n)
+        .filter(|&i| {
+            let prev = points[(i + n - 1) % n];
+            let cur = points[i];
+            let next = points[(i + 1) % n];
+            cross(cur.sub(prev), next.sub(cur)).abs() > 1e-9
+        })
+        .map(|i| points[i])
+        .collect()
+}
+
+// 符号付き面積の2倍（y軸下向きの座標系で時計回りが正）
+fn signed_area_f(points: &[PointF]) -> f64 {
+    let n = points.len();
+    (0..n).map(|i| cross(points[i], points[(i + 1) % n])).sum()
 }
 
-fn points_equal(s: &Segment, pt: Point) -> bool {
-    (s.p1.x == pt.x && s.p1.y == pt.y) || (s.p2.x == pt.x && s.p2.y == pt.y)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ループを nonzero 規則でラスタライズし直す（ピクセル中心から右へ伸ばした半直線と縦の辺の交差を数える）
+    fn rasterize(loops: &[OutlineLoop], width: usize, height: usize) -> Vec<u8> {
+        let mut mask = vec![0u8; width * height];
+        for y in 0..height {
+            let cy = y as f64 + 0.5;
+            for x in 0..width {
+                let cx = x as f64 + 0.5;
+                let mut winding = 0;
+                for outline in loops {
+                    let n = outline.points.len();
+                    for i in 0..n {
+                        let a = outline.points[i];
+                        let b = outline.points[(i + 1) % n];
+                        if a.x != b.x || (a.x as f64) < cx {
+                            continue;
+                        }
+                        let (y0, y1) = (a.y.min(b.y) as f64, a.y.max(b.y) as f64);
+                        if cy > y0 && cy < y1 {
+                            winding += if b.y > a.y { 1 } else { -1 };
+                        }
+                    }
+                }
+                mask[y * width + x] = (winding != 0) as u8;
+            }
+        }
+        mask
+    }
+
+    fn points(coords: &[(i32, i32)]) -> Vec<Point> {
+        coords.iter().map(|&(x, y)| Point { x, y }).collect()
+    }
+
+    // 決まった種から作る疑似乱数のマスク
+    fn noise_mask(width: usize, height: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..width * height)
+            .map(|_| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                (state >> 31) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn single_pixel_is_one_square_loop() {
+        let mut mask = vec![0u8; 9];
+        mask[4] = 1;
+
+        let loops = trace_mask_outline(&mask, 3, 3);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].points, points(&[(1, 1), (2, 1), (2, 2), (1, 2)]));
+        assert_eq!(
+            mask_to_path(&mask, 3, 3, 0.0, 0.0).unwrap(),
+            "M 1 1 L 2 1 L 2 2 L 1 2 Z"
+        );
+    }
+
+    #[test]
+    fn straight_runs_keep_only_corners() {
+        let mask = vec![1u8; 4 * 3];
+
+        let loops = trace_mask_outline(&mask, 4, 3);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].points, points(&[(0, 0), (4, 0), (4, 3), (0, 3)]));
+    }
+
+    #[test]
+    fn hole_becomes_its_own_loop() {
+        let mut mask = vec![1u8; 9];
+        mask[4] = 0;
+
+        let loops = trace_mask_outline(&mask, 3, 3);
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].points, points(&[(0, 0), (3, 0), (3, 3), (0, 3)]));
+        assert_eq!(loops[1].points, points(&[(1, 1), (1, 2), (2, 2), (2, 1)]));
+        assert_eq!(rasterize(&loops, 3, 3), mask);
+    }
+
+    #[test]
+    fn checkerboard_pixels_are_separate_loops() {
+        let mask = [1u8, 0, 1, 0, 1, 0, 1, 0, 1];
+
+        let loops = trace_mask_outline(&mask, 3, 3);
+        assert_eq!(loops.len(), 5);
+        assert!(loops.iter().all(|l| l.points.len() == 4));
+        assert_eq!(rasterize(&loops, 3, 3), mask);
+    }
+
+    #[test]
+    fn empty_and_full_masks() {
+        assert!(trace_mask_outline(&[0u8; 12], 4, 3).is_empty());
+        assert_eq!(mask_to_path(&[0u8; 12], 4, 3, 0.0, 0.0).unwrap(), "");
+
+        let loops = trace_mask_outline(&[1u8; 12], 4, 3);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(rasterize(&loops, 4, 3), vec![1u8; 12]);
+    }
+
+    #[test]
+    fn outline_rasterizes_back_to_mask() {
+        for seed in 0..16 {
+            let (w, h) = (17, 13);
+            let mask = noise_mask(w, h, seed);
+            let loops = trace_mask_outline(&mask, w as u32, h as u32);
+            assert_eq!(rasterize(&loops, w, h), mask, "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn path_applies_offset() {
+        let path = mask_to_path(&[1u8], 1, 1, 10.0, 20.5).unwrap();
+        assert_eq!(path, "M 10 20.5 L 11 20.5 L 11 21.5 L 10 21.5 Z");
+    }
+
+    #[test]
+    fn outer_loops_are_clockwise_and_holes_counter_clockwise() {
+        // 5x5 の枠（中に 3x3 の穴）
+        let mut mask = vec![1u8; 25];
+        for y in 1..4 {
+            for x in 1..4 {
+                mask[y * 5 + x] = 0;
+            }
+        }
+
+        let loops = trace_mask_outline(&mask, 5, 5);
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].kind, LoopKind::Outer);
+        assert_eq!(signed_area2(&loops[0].points), 2 * 25);
+        assert_eq!(loops[1].kind, LoopKind::Hole);
+        assert_eq!(signed_area2(&loops[1].points), -2 * 9);
+
+        let polylines = mask_to_polylines(&mask, 5, 5, 0.0, 0.0).unwrap();
+        assert_eq!(
+            polylines.kinds(),
+            vec![LoopKind::Outer as u8, LoopKind::Hole as u8]
+        );
+        assert_eq!(polylines.offsets(), vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn pixels_touching_at_a_corner_are_separate_outer_loops() {
+        let mask = [1u8, 0, 0, 1];
+
+        let loops = trace_mask_outline(&mask, 2, 2);
+        assert_eq!(loops.len(), 2);
+        for outline in &loops {
+            assert_eq!(outline.kind, LoopKind::Outer);
+            assert_eq!(signed_area2(&outline.points), 2);
+        }
+        assert_eq!(loops[0].points, points(&[(0, 0), (1, 0), (1, 1), (0, 1)]));
+        assert_eq!(loops[1].points, points(&[(1, 1), (2, 1), (2, 2), (1, 2)]));
+    }
+
+    #[test]
+    fn hole_pixels_touching_at_a_corner_form_one_hole() {
+        // 非選択側は8連結なので、角で接する2つの穴は1本のループになる
+        let mut mask = vec![1u8; 16];
+        mask[5] = 0;
+        mask[10] = 0;
+
+        let loops = trace_mask_outline(&mask, 4, 4);
+        assert_eq!(loops.len(), 2);
+        assert_eq!(loops[0].kind, LoopKind::Outer);
+        assert_eq!(loops[1].kind, LoopKind::Hole);
+        assert_eq!(signed_area2(&loops[1].points), -2 * 2);
+        assert_eq!(rasterize(&loops, 4, 4), mask);
+    }
+
+    #[test]
+    fn pick_direction_prefers_right_turn() {
+        // 右へ進んできて、下（右折）と右（直進）の両方がある
+        assert_eq!(
+            pick_direction(EDGE_DOWN | EDGE_RIGHT, EDGE_RIGHT),
+            EDGE_DOWN
+        );
+        // 直進と左折なら直進
+        assert_eq!(pick_direction(EDGE_UP | EDGE_RIGHT, EDGE_RIGHT), EDGE_RIGHT);
+        assert_eq!(pick_direction(EDGE_UP, EDGE_RIGHT), EDGE_UP);
+        // 始点では最下位のビット
+        assert_eq!(pick_direction(EDGE_LEFT | EDGE_DOWN, 0), EDGE_DOWN);
+        assert_eq!(turn_right(EDGE_UP), EDGE_RIGHT);
+    }
+
+    // 中心が半径 r の円に入るピクセルを選択したマスク
+    fn disk_mask(size: usize, r: f64) -> Vec<u8> {
+        let c = size as f64 / 2.0;
+        (0..size * size)
+            .map(|i| {
+                let x = (i % size) as f64 + 0.5 - c;
+                let y = (i / size) as f64 + 0.5 - c;
+                (x * x + y * y <= r * r) as u8
+            })
+            .collect()
+    }
+
+    fn square_mask(size: usize, x0: usize, x1: usize) -> Vec<u8> {
+        (0..size * size)
+            .map(|i| {
+                let (x, y) = (i % size, i / size);
+                (x >= x0 && x < x1 && y >= x0 && y < x1) as u8
+            })
+            .collect()
+    }
+
+    // 曲線ループを区間ごとに steps 分割した点列
+    fn curve_samples(curve: &CurveLoop, steps: usize) -> Vec<PointF> {
+        let mut samples = Vec::new();
+        let mut cur = curve.start;
+        for segment in &curve.segments {
+            for k in 0..=steps {
+                let t = k as f64 / steps as f64;
+                samples.push(match *segment {
+                    CurveSegment::Corner { vertex, .. } if t < 0.5 => {
+                        PointF::lerp(cur, vertex, t * 2.0)
+                    }
+                    CurveSegment::Corner { vertex, end } => {
+                        PointF::lerp(vertex, end, t * 2.0 - 1.0)
+                    }
+                    CurveSegment::Bezier { c1, c2, end } => bezier_point(cur, c1, c2, end, t),
+                });
+            }
+            cur = match *segment {
+                CurveSegment::Corner { end, .. } | CurveSegment::Bezier { end, .. } => end,
+            };
+        }
+        samples
+    }
+
+    // 閉じた折れ線までの距離
+    fn distance_to_loop(p: PointF, points: &[PointF]) -> f64 {
+        let n = points.len();
+        (0..n)
+            .map(|i| distance_to_segment(p, points[i], points[(i + 1) % n]))
+            .fold(f64::MAX, f64::min)
+    }
+
+    fn corner_count(curve: &CurveLoop) -> usize {
+        curve
+            .segments
+            .iter()
+            .filter(|s| matches!(s, CurveSegment::Corner { .. }))
+            .count()
+    }
+
+    #[test]
+    fn large_square_keeps_its_corners() {
+        let mask = square_mask(14, 2, 12);
+
+        let curves = trace_mask_curves(&mask, 14, 14, 1.0, 0.2);
+        assert_eq!(curves.len(), 1);
+        let vertices: Vec<PointF> = curves[0]
+            .segments
+            .iter()
+            .filter_map(|s| match *s {
+                CurveSegment::Corner { vertex, .. } => Some(vertex),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            vertices,
+            [(2.0, 2.0), (12.0, 2.0), (12.0, 12.0), (2.0, 12.0)].map(|(x, y)| PointF::new(x, y))
+        );
+    }
+
+    #[test]
+    fn alpha_max_controls_corner_detection() {
+        let square = square_mask(14, 2, 12);
+        let disk = disk_mask(32, 11.0);
+
+        // 0 なら全て角
+        let curves = trace_mask_curves(&disk, 32, 32, 0.0, 0.2);
+        assert_eq!(corner_count(&curves[0]), curves[0].segments.len());
+        // 4/3 を超えれば角なし
+        let curves = trace_mask_curves(&square, 14, 14, 1.34, 0.2);
+        assert_eq!(corner_count(&curves[0]), 0);
+        // 既定値では円に角はできない
+        let curves = trace_mask_curves(&disk, 32, 32, 1.0, 0.2);
+        assert_eq!(corner_count(&curves[0]), 0);
+    }
+
+    #[test]
+    fn curve_stays_close_to_polygon() {
+        let mask = disk_mask(32, 11.0);
+        let outline = trace_mask_outline(&mask, 32, 32);
+        let polygon = best_polygon(&expand_unit_path(&outline[0].points));
+
+        let curves = trace_mask_curves(&mask, 32, 32, 1.0, 0.0);
+        assert_eq!(curves[0].segments.len(), polygon.len());
+        for p in curve_samples(&curves[0], 32) {
+            assert!(distance_to_loop(p, &polygon) <= 0.5, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn merged_curves_stay_within_tolerance() {
+        let mask = disk_mask(32, 11.0);
+        let tolerance = 0.2;
+
+        let original = trace_mask_curves(&mask, 32, 32, 1.0, 0.0);
+        let merged = trace_mask_curves(&mask, 32, 32, 1.0, tolerance);
+        assert!(merged[0].segments.len() < original[0].segments.len());
+
+        let dense = curve_samples(&merged[0], 64);
+        for p in curve_samples(&original[0], 32) {
+            assert!(distance_to_loop(p, &dense) <= tolerance, "{p:?}");
+        }
+    }
+
+    #[test]
+    fn smooth_path_writes_offset_coordinates() {
+        let mask = square_mask(14, 2, 12);
+        let path = mask_to_smooth_path(&mask, 14, 14, 0.5, 1.0, 1.0, 0.2).unwrap();
+        assert_eq!(
+            path,
+            "M 2.5 8 L 2.5 3 L 7.5 3 L 12.5 3 L 12.5 8 L 12.5 13 L 7.5 13 L 2.5 13 L 2.5 8 Z"
+        );
+    }
+
+    fn iso_area2(iso: &IsoLoop) -> f64 {
+        signed_area_f(&iso.points)
+    }
+
+    #[test]
+    fn isoline_crosses_between_pixel_centres() {
+        let mut mask = vec![0u8; 9];
+        mask[4] = 255;
+
+        let loops = trace_soft_mask_isolines(&mask, 3, 3, 128);
+        assert_eq!(loops.len(), 1);
+        assert_eq!(loops[0].kind, LoopKind::Outer);
+        // 中心 (1.5, 1.5) から上下左右に (255 - 128) / 255 だけ離れた菱形
+        let d = 127.0 / 255.0;
+        let mut expected = vec![
+            (1.5 + d, 1.5),
+            (1.5, 1.5 + d),
+            (1.5 - d, 1.5),
+            (1.5, 1.5 - d),
+        ];
+        let mut got: Vec<(f64, f64)> = loops[0].points.iter().map(|p| (p.x, p.y)).collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        got.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        for (g, e) in got.iter().zip(&expected) {
+            assert!(
+                (g.0 - e.0).abs() < 1e-9 && (g.1 - e.1).abs() < 1e-9,
+                "{got:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn isoline_outer_is_clockwise_and_hole_counter_clockwise() {
+        // 5x5 の中央 3x3 が選択され、その中心だけ抜けている
+        let mut mask = vec![0u8; 25];
+        for y in 1..4 {
+            for x in 1..4 {
+                mask[y * 5 + x] = 255;
+            }
+        }
+        mask[12] = 0;
+
+        let loops = trace_soft_mask_isolines(&mask, 5, 5, 128);
+        assert_eq!(loops.len(), 2);
+        let outer = loops.iter().find(|l| l.kind == LoopKind::Outer).unwrap();
+        let hole = loops.iter().find(|l| l.kind == LoopKind::Hole).unwrap();
+        assert!(iso_area2(outer) > 0.0);
+        assert!(iso_area2(hole) < 0.0);
+        assert!(iso_area2(outer) > -iso_area2(hole));
+    }
+
+    #[test]
+    fn saddle_cell_uses_the_centre_average() {
+        // 対角の2ピクセル。4ピクセルの中心の平均は 127.5
+        let mask = [255u8, 0, 0, 255];
+
+        let split = trace_soft_mask_isolines(&mask, 2, 2, 128);
+        assert_eq!(split.len(), 2);
+        assert!(split.iter().all(|l| l.kind == LoopKind::Outer));
+
+        let joined = trace_soft_mask_isolines(&mask, 2, 2, 100);
+        assert_eq!(joined.len(), 1);
+        assert_eq!(joined[0].kind, LoopKind::Outer);
+    }
+
+    #[test]
+    fn zero_threshold_still_outlines_the_mask() {
+        let mask = vec![255u8; 4];
+
+        let path = soft_mask_to_path(&mask, 2, 2, Some(0), 0.0, 0.0).unwrap();
+        assert!(!path.is_empty());
+        assert_eq!(
+            path,
+            soft_mask_to_path(&mask, 2, 2, Some(1), 0.0, 0.0).unwrap()
+        );
+        let polylines = soft_mask_to_polylines(&mask, 2, 2, Some(0), 0.0, 0.0).unwrap();
+        assert_eq!(polylines.kinds(), vec![LoopKind::Outer as u8]);
+    }
+
+    #[test]
+    fn full_viewport_matches_mask_to_path() {
+        let mask = noise_mask(9, 7, 3);
+        let full = mask_to_path(&mask, 9, 7, 0.0, 0.0).unwrap();
+        assert_eq!(
+            mask_to_path_in_viewport(&mask, 9, 7, 0.0, 0.0, 9.0, 7.0, 1.0).unwrap(),
+            full
+        );
+        // はみ出した表示範囲はキャンバスに切り詰められ、座標は表示範囲の原点基準になる
+        assert_eq!(
+            mask_to_path_in_viewport(&mask, 9, 7, -5.0, -5.0, 30.0, 30.0, 1.0).unwrap(),
+            mask_to_path(&mask, 9, 7, 5.0, 5.0).unwrap()
+        );
+    }
+
+    #[test]
+    fn partial_viewport_emits_open_paths_in_screen_coordinates() {
+        // 4x2 がすべて選択されたマスクの右半分だけを 2 倍で表示する
+        let mask = vec![1u8; 8];
+        let path = mask_to_path_in_viewport(&mask, 4, 2, 2.0, 0.0, 2.0, 2.0, 2.0).unwrap();
+        // 左端の辺は表示範囲の境界上にないので出力されず、上・右・下の3辺が1本の開いた折れ線になる
+        assert_eq!(path, "M 0 0 L 4 0 L 4 4 L 0 4");
+        assert!(!path.contains('Z'));
+    }
+
+    #[test]
+    fn empty_viewport_has_no_path() {
+        let mask = vec![1u8; 8];
+        for (x, y, w, h) in [
+            (0.0, 0.0, 0.0, 0.0),
+            (10.0, 10.0, 5.0, 5.0),
+            (-8.0, 0.0, 4.0, 2.0),
+        ] {
+            assert_eq!(
+                mask_to_path_in_viewport(&mask, 4, 2, x, y, w, h, 1.0).unwrap(),
+                ""
+            );
+        }
+    }
 }
@@ -0,0 +1,330 @@
+use wasm_bindgen::prelude::*;
+
+use crate::utils::composite::blend_source_over;
+use crate::utils::validate::{check_mask_len, check_rgba_len};
+
+/// 境界線を描く位置
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StrokePosition {
+    /// 選択範囲の内側（キャンバスの端も境界とみなす）
+    Inside = 0,
+    /// 境界をまたぐ（奇数幅の余りは内側）
+    Center = 1,
+    /// 選択範囲の外側
+    Outside = 2,
+}
+
+/// 選択範囲の境界線をレイヤーのピクセルバッファに描画する（選択範囲の境界線を描く）
+/// buffer: 描画先のピクセルバッファ (RGBA)
+/// mask: 選択範囲のマスク（0 以外を選択とみなす。MaskFormat::Binary と同じ）
+/// stroke_width: 線幅（ピクセル）
+/// position: 線の位置
+/// r, g, b, a: 線の色（source-overで重ねる）
+///
+/// 境界からの距離はピクセル中心間のユークリッド距離で測り、距離が線幅以下のピクセルを塗る。
+/// 線幅1では上下左右の隣接だけが距離1になるので、角が二重にならない1ピクセルの線になる。
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
+pub fn stroke_selection(
+    buffer: &mut [u8],
+    mask: &[u8],
+    width: u32,
+    height: u32,
+    stroke_width: u32,
+    position: StrokePosition,
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
+) -> Result<(), JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+    check_mask_len("mask", mask, width, height)?;
+
+    let w = width as usize;
+    let h = height as usize;
+
+    let (inner_width, outer_width) = match position {
+        StrokePosition::Inside => (stroke_width, 0),
+        StrokePosition::Outside => (0, stroke_width),
+        StrokePosition::Center => (stroke_width.div_ceil(2), stroke_width / 2),
+    };
+    if inner_width == 0 && outer_width == 0 {
+        return Ok(());
+    }
+
+    let is_selected = |idx: usize| mask[idx] != 0;
+
+    // 内側: 非選択ピクセル（キャンバス外を含む）までの距離
+    let inner = if inner_width > 0 {
+        squared_distance_field(w, h, |x, y| match (x, y) {
+            (Some(x), Some(y)) => !is_selected(y * w + x),
+            _ => true,
+        })
+    } else {
+        Vec::new()
+    };
+
+    // 外側: 選択ピクセルまでの距離
+    let outer = if outer_width > 0 {
+        squared_distance_field(w, h, |x, y| match (x, y) {
+            (Some(x), Some(y)) => is_selected(y * w + x),
+            _ => false,
+        })
+    } else {
+        Vec::new()
+    };
+
+    let inner_limit = (inner_width as f32) * (inner_width as f32);
+    let outer_limit = (outer_width as f32) * (outer_width as f32);
+    let color = [r, g, b, a];
+
+    for idx in 0..w * h {
+        let on_stroke = if is_selected(idx) {
+            inner_width > 0 && inner[idx] <= inner_limit
+        } else {
+            outer_width > 0 && outer[idx] <= outer_limit
+        };
+
+        if on_stroke {
+            let p = idx * 4;
+            blend_source_over(&mut buffer[p..p + 4], color, 255);
+        }
+    }
+
+    Ok(())
+}
+
+/// 各ピクセルから最も近い「特徴ピクセル」までの二乗ユークリッド距離（Felzenszwalb-Huttenlocher）
+/// is_feature にはキャンバス外の1ピクセル幅の枠も None 座標として問い合わせる
+fn squared_distance_field<F>(w: usize, h: usize, is_feature: F) -> Vec<f32>
+where
+    F: Fn(Option<usize>, Option<usize>) -> bool,
+{
+    const INF: f32 = 1e20;
+
+    // 1ピクセルの枠を足したグリッド
+    let pw = w + 2;
+    let ph = h + 2;
+    let coord = |v: usize, len: usize| -> Option<usize> {
+        if v == 0 || v > len { None } else { Some(v - 1) }
+    };
+
+    let mut grid = vec![INF; pw * ph];
+    for py in 0..ph {
+        for px in 0..pw {
+            if is_feature(coord(px, w), coord(py, h)) {
+                grid[py * pw + px] = 0.0;
+            }
+        }
+    }
+
+    let n = pw.max(ph);
+    let mut f = vec![0.0f32; n];
+    let mut d = vec![0.0f32; n];
+    let mut v = vec![0usize; n];
+    let mut z = vec![0.0f32; n + 1];
+
+    // 縦方向
+    for px in 0..pw {
+        for py in 0..ph {
+            f[py] = grid[py * pw + px];
+        }
+        distance_1d(&f[..ph], &mut d[..ph], &mut v, &mut z);
+        for py in 0..ph {
+            grid[py * pw + px] = d[py];
+        }
+    }
+
+    // 横方向
+    for py in 0..ph {
+        let row = &mut grid[py * pw..(py + 1) * pw];
+        f[..pw].copy_from_slice(row);
+        distance_1d(&f[..pw], &mut d[..pw], &mut v, &mut z);
+        row.copy_from_slice(&d[..pw]);
+    }
+
+    // 枠を取り除く
+    let mut result = Vec::with_capacity(w * h);
+    for y in 0..h {
+        let start = (y + 1) * pw + 1;
+        result.extend_from_slice(&grid[start..start + w]);
+    }
+    result
+}
+
+/// 1次元の二乗距離変換（放物線の下側包絡線）
+fn distance_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    let n = f.len();
+    if n == 0 {
+        return;
+    }
+
+    let mut k = 0usize;
+    v[0] = 0;
+    z[0] = f32::NEG_INFINITY;
+    z[1] = f32::INFINITY;
+
+    for q in 1..n {
+        let mut s;
+        loop {
+            let p = v[k];
+            s = ((f[q] + (q * q) as f32) - (f[p] + (p * p) as f32)) / (2.0 * (q - p) as f32);
+            // z[0] は -∞ なので k が 0 を下回ることはない
+            if s <= z[k] {
+                k -= 1;
+            } else {
+                break;
+            }
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = f32::INFINITY;
+    }
+
+    k = 0;
+    for (q, dq) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let p = v[k];
+        let diff = q as f32 - p as f32;
+        *dq = diff * diff + f[p];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 7x7 の中央 3x3 が選択されたマスク
+    fn square_mask() -> Vec<u8> {
+        let mut mask = vec![0u8; 49];
+        for y in 2..5 {
+            for x in 2..5 {
+                mask[y * 7 + x] = 1;
+            }
+        }
+        mask
+    }
+
+    // 線が描かれたピクセルを '#' にした行
+    fn stroke_rows(
+        mask: &[u8],
+        w: u32,
+        h: u32,
+        stroke_width: u32,
+        position: StrokePosition,
+    ) -> Vec<String> {
+        let mut buffer = vec![0u8; (w * h * 4) as usize];
+        stroke_selection(
+            &mut buffer,
+            mask,
+            w,
+            h,
+            stroke_width,
+            position,
+            255,
+            0,
+            0,
+            255,
+        )
+        .unwrap();
+        buffer
+            .chunks(w as usize * 4)
+            .map(|row| {
+                row.chunks(4)
+                    .map(|px| if px[3] != 0 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn inside_stroke_stays_in_the_selection() {
+        assert_eq!(
+            stroke_rows(&square_mask(), 7, 7, 1, StrokePosition::Inside),
+            [
+                ".......", ".......", "..###..", "..#.#..", "..###..", ".......", "......."
+            ]
+        );
+    }
+
+    #[test]
+    fn outside_stroke_of_width_one_skips_corners() {
+        assert_eq!(
+            stroke_rows(&square_mask(), 7, 7, 1, StrokePosition::Outside),
+            [
+                ".......", "..###..", ".#...#.", ".#...#.", ".#...#.", "..###..", "......."
+            ]
+        );
+    }
+
+    #[test]
+    fn wider_outside_stroke_rounds_the_corners() {
+        assert_eq!(
+            stroke_rows(&square_mask(), 7, 7, 2, StrokePosition::Outside),
+            [
+                "..###..", ".#####.", "##...##", "##...##", "##...##", ".#####.", "..###.."
+            ]
+        );
+    }
+
+    #[test]
+    fn center_stroke_straddles_the_edge() {
+        // 幅2は内側1・外側1に分かれる
+        let mut expected = stroke_rows(&square_mask(), 7, 7, 1, StrokePosition::Inside);
+        let outside = stroke_rows(&square_mask(), 7, 7, 1, StrokePosition::Outside);
+        for (row, out) in expected.iter_mut().zip(&outside) {
+            *row = row
+                .chars()
+                .zip(out.chars())
+                .map(|(a, b)| if a == '#' || b == '#' { '#' } else { '.' })
+                .collect();
+        }
+        assert_eq!(
+            stroke_rows(&square_mask(), 7, 7, 2, StrokePosition::Center),
+            expected
+        );
+        // 幅1の余りは内側に入る
+        assert_eq!(
+            stroke_rows(&square_mask(), 7, 7, 1, StrokePosition::Center),
+            stroke_rows(&square_mask(), 7, 7, 1, StrokePosition::Inside)
+        );
+    }
+
+    #[test]
+    fn canvas_border_counts_as_an_edge() {
+        let mask = vec![1u8; 16];
+        assert_eq!(
+            stroke_rows(&mask, 4, 4, 1, StrokePosition::Inside),
+            ["####", "#..#", "#..#", "####"]
+        );
+        assert_eq!(
+            stroke_rows(&mask, 4, 4, 1, StrokePosition::Outside),
+            ["....", "....", "....", "...."]
+        );
+    }
+
+    #[test]
+    fn stroke_is_blended_over_the_buffer() {
+        let mut buffer = vec![0, 0, 255, 255, 0, 0, 255, 255];
+        stroke_selection(
+            &mut buffer,
+            &[1, 0],
+            2,
+            1,
+            1,
+            StrokePosition::Inside,
+            255,
+            0,
+            0,
+            128,
+        )
+        .unwrap();
+        assert_eq!(&buffer[4..], &[0, 0, 255, 255]);
+        assert_eq!(buffer[3], 255);
+        assert!(buffer[0] > 120 && buffer[2] > 120);
+    }
+}
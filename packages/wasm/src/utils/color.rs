@@ -0,0 +1,81 @@
+// sRGB と linear light の変換
+// linear light の選択肢があるのは合成（compositor）とぼかし（filters::blur）だけ。
+// 拡大縮小（リサンプリング）は @sledge/anvil の blitFromBuffer が行っていてこのクレートにはないので対象外
+
+use std::sync::OnceLock;
+
+/// sRGB の値 (0.0-1.0) を linear light (0.0-1.0) に変換する
+#[inline]
+pub fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// linear light の値 (0.0-1.0) を sRGB (0.0-1.0) に変換する
+#[inline]
+pub fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// sRGB の 8bit 値を linear light (0.0-1.0) に変換する（テーブル参照）
+#[inline]
+pub fn srgb_byte_to_linear(v: u8) -> f32 {
+    static TABLE: OnceLock<[f32; 256]> = OnceLock::new();
+    TABLE.get_or_init(|| std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0)))[v as usize]
+}
+
+/// linear light の値を sRGB の 8bit 値に変換する（範囲外はクランプ）
+///
+/// 変換はすべて f32 の決まった式なので、同じ入力からは常に同じ結果になる（アンドゥ・リドゥの再実行でも一致する）
+#[inline]
+pub fn linear_to_srgb_byte(v: f32) -> u8 {
+    (linear_to_srgb(v.clamp(0.0, 1.0)) * 255.0).round() as u8
+}
+
+/// linear light の RGB を OKLab [L, a, b] に変換する
+///
+/// OKLab は知覚的にほぼ均等な色空間で、色の距離や平均を人の見え方に近い形で扱える
+#[inline]
+pub fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b).cbrt();
+    let m = (0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b).cbrt();
+    let s = (0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b).cbrt();
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+/// OKLab [L, a, b] を linear light の RGB に変換する（sRGB の範囲外になることもある）
+#[inline]
+pub fn oklab_to_linear([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+    let m_ = l - 0.105_561_346 * a - 0.063_854_17 * b;
+    let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+    let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+    [
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+}
+
+/// sRGB の 8bit 値の色を OKLab に変換する
+#[inline]
+pub fn srgb_bytes_to_oklab(rgb: [u8; 3]) -> [f32; 3] {
+    linear_to_oklab(rgb.map(srgb_byte_to_linear))
+}
+
+/// OKLab の色を sRGB の 8bit 値に変換する（範囲外はクランプ）
+#[inline]
+pub fn oklab_to_srgb_bytes(lab: [f32; 3]) -> [u8; 3] {
+    oklab_to_linear(lab).map(linear_to_srgb_byte)
+}
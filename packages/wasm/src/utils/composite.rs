@@ -0,0 +1,36 @@
+/// ストレートアルファのRGBAピクセルに色を source-over で重ねる
+/// dst: 書き込み先ピクセル（4バイト）
+/// src: 重ねる色
+/// coverage: src のアルファに掛ける被覆率 (0-255)
+#[inline]
+pub fn blend_source_over(dst: &mut [u8], src: [u8; 4], coverage: u8) {
+    let sa = src[3] as f32 * coverage as f32 / (255.0 * 255.0);
+    if sa <= 0.0 {
+        return;
+    }
+
+    let da = dst[3] as f32 / 255.0;
+    let out_a = sa + da * (1.0 - sa);
+
+    for c in 0..3 {
+        let value = (src[c] as f32 * sa + dst[c] as f32 * da * (1.0 - sa)) / out_a;
+        dst[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+    dst[3] = (out_a * 255.0).round().clamp(0.0, 255.0) as u8;
+}
+
+/// ストレートアルファのRGBAピクセルに色を source-atop で重ねる（透明部分の保護）
+/// dst のアルファは変えずに色だけを src に近づける。dst が透明なら何もしない
+/// coverage: src のアルファに掛ける被覆率 (0-255)
+#[inline]
+pub fn blend_source_atop(dst: &mut [u8], src: [u8; 4], coverage: u8) {
+    let sa = src[3] as f32 * coverage as f32 / (255.0 * 255.0);
+    if sa <= 0.0 || dst[3] == 0 {
+        return;
+    }
+
+    for c in 0..3 {
+        let value = src[c] as f32 * sa + dst[c] as f32 * (1.0 - sa);
+        dst[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+}
@@ -1,8 +1,12 @@
 use wasm_bindgen::prelude::*;
 
+use crate::utils::validate::check_rgba_len;
+
 // RGBAピクセルバッファから不透明部分のマスクを作成
 #[wasm_bindgen]
-pub fn create_opacity_mask(buffer: &[u8], width: u32, height: u32) -> Vec<u8> {
+pub fn create_opacity_mask(buffer: &[u8], width: u32, height: u32) -> Result<Vec<u8>, JsError> {
+    check_rgba_len("buffer", buffer, width, height)?;
+
     let total_pixels = (width * height) as usize;
     let mut mask = vec![0u8; total_pixels];
 
@@ -17,5 +21,68 @@ pub fn create_opacity_mask(buffer: &[u8], width: u32, height: u32) -> Vec<u8> {
         }
     }
 
-    mask
+    Ok(mask)
+}
+
+/// 選択マスクの値の形式
+///
+/// 2値マスクと被覆率のマスクは同じ 0-255 の範囲の値を使うので、値からは区別できない。
+/// マスクを受け取る関数は形式を引数で受け取り、それに従って読む
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaskFormat {
+    /// 0/1 の2値（0 以外はすべて完全に選択とみなす）
+    Binary = 0,
+    /// 0-255 の被覆率（255 で完全に選択）
+    Coverage = 1,
+}
+
+impl MaskFormat {
+    /// マスク値を 0-255 の被覆率として読む
+    #[inline]
+    pub fn coverage(self, value: u8) -> u8 {
+        match self {
+            MaskFormat::Binary => {
+                if value != 0 {
+                    255
+                } else {
+                    0
+                }
+            }
+            MaskFormat::Coverage => value,
+        }
+    }
+
+    /// 0-255 の被覆率をこの形式のマスク値にする（2値では半分以上を選択とみなす）
+    #[inline]
+    pub fn from_coverage(self, coverage: u8) -> u8 {
+        match self {
+            MaskFormat::Binary => (coverage >= 128) as u8,
+            MaskFormat::Coverage => coverage,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_mask_treats_any_nonzero_as_selected() {
+        assert_eq!(MaskFormat::Binary.coverage(0), 0);
+        assert_eq!(MaskFormat::Binary.coverage(1), 255);
+        assert_eq!(MaskFormat::Binary.coverage(200), 255);
+        assert_eq!(MaskFormat::Binary.from_coverage(255), 1);
+        assert_eq!(MaskFormat::Binary.from_coverage(0), 0);
+    }
+
+    #[test]
+    fn coverage_mask_keeps_faint_values() {
+        // 1/255 の被覆率が完全な選択にならない
+        assert_eq!(MaskFormat::Coverage.coverage(1), 1);
+        for v in 0..=255u8 {
+            let format = MaskFormat::Coverage;
+            assert_eq!(format.from_coverage(format.coverage(v)), v);
+        }
+    }
 }
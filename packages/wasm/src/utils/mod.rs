@@ -1 +1,10 @@
+// 選択範囲マスクの形式
 pub mod mask;
+// ピクセル合成
+pub mod composite;
+// 入力値の検証
+pub mod validate;
+// 色空間の変換（sRGB / linear light）
+pub mod color;
+// 矩形
+pub mod rect;
@@ -0,0 +1,41 @@
+use wasm_bindgen::prelude::*;
+
+/// 軸平行な矩形（ピクセル単位）
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// width x height の画像に収まる部分を (x0, y0, x1, y1) で返す（x1, y1 は含まない）
+    pub fn clip_to(&self, width: u32, height: u32) -> Option<(usize, usize, usize, usize)> {
+        let x1 = self.x.saturating_add(self.width).min(width);
+        let y1 = self.y.saturating_add(self.height).min(height);
+        if self.x >= x1 || self.y >= y1 {
+            return None;
+        }
+        Some((self.x as usize, self.y as usize, x1 as usize, y1 as usize))
+    }
+}
+
+/// mask_to_rects 形式の [x, y, width, height, ...] を Rect の列に戻す
+pub(crate) fn rects_from_flat(name: &str, flat: &[u32]) -> Result<Vec<Rect>, JsError> {
+    if !flat.len().is_multiple_of(4) {
+        return Err(JsError::new(&format!(
+            "{name}: length {} is not a multiple of 4",
+            flat.len()
+        )));
+    }
+    Ok(flat
+        .chunks_exact(4)
+        .map(|c| Rect {
+            x: c[0],
+            y: c[1],
+            width: c[2],
+            height: c[3],
+        })
+        .collect())
+}
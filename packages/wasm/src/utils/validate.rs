@@ -0,0 +1,140 @@
+use wasm_bindgen::prelude::*;
+
+/// width * height を計算する（オーバーフローはエラー）
+pub fn pixel_count(width: u32, height: u32) -> Result<usize, JsError> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .ok_or_else(|| JsError::new(&format!("image size {width}x{height} is too large")))
+}
+
+/// マスクの長さが width * height と一致するか確認する
+pub fn check_mask_len(name: &str, mask: &[u8], width: u32, height: u32) -> Result<(), JsError> {
+    let expected = pixel_count(width, height)?;
+    if mask.len() != expected {
+        return Err(JsError::new(&format!(
+            "{name}: length {} does not match {width}x{height} (expected {expected})",
+            mask.len()
+        )));
+    }
+    Ok(())
+}
+
+/// RGBAバッファの長さが width * height * 4 と一致するか確認する
+pub fn check_rgba_len(name: &str, buffer: &[u8], width: u32, height: u32) -> Result<(), JsError> {
+    let expected = pixel_count(width, height)?
+        .checked_mul(4)
+        .ok_or_else(|| JsError::new(&format!("image size {width}x{height} is too large")))?;
+    if buffer.len() != expected {
+        return Err(JsError::new(&format!(
+            "{name}: length {} does not match {width}x{height} RGBA (expected {expected})",
+            buffer.len()
+        )));
+    }
+    Ok(())
+}
+
+/// 同じサイズのRGBAバッファを layer_count 枚連結したものか確認し、1枚あたりの長さを返す
+pub fn check_layers_len(
+    name: &str,
+    layers: &[u8],
+    width: u32,
+    height: u32,
+    layer_count: u32,
+) -> Result<usize, JsError> {
+    let layer_len = pixel_count(width, height)?
+        .checked_mul(4)
+        .ok_or_else(|| JsError::new(&format!("image size {width}x{height} is too large")))?;
+    let expected = layer_len.checked_mul(layer_count as usize).ok_or_else(|| {
+        JsError::new(&format!(
+            "{layer_count} layers of {width}x{height} are too large"
+        ))
+    })?;
+    if layers.len() != expected {
+        return Err(JsError::new(&format!(
+            "{name}: length {} does not match {layer_count} layers of {width}x{height} RGBA (expected {expected})",
+            layers.len()
+        )));
+    }
+    Ok(layer_len)
+}
+
+/// 2つのバッファの長さが一致するか確認する
+pub fn check_same_len(name_a: &str, a: &[u8], name_b: &str, b: &[u8]) -> Result<(), JsError> {
+    if a.len() != b.len() {
+        return Err(JsError::new(&format!(
+            "{name_a} (length {}) and {name_b} (length {}) must have the same length",
+            a.len(),
+            b.len()
+        )));
+    }
+    Ok(())
+}
+
+/// 数値が有限か確認する
+pub fn check_finite(name: &str, value: f32) -> Result<(), JsError> {
+    if !value.is_finite() {
+        return Err(JsError::new(&format!("{name} must be a finite number")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    // エラーになるか（ネイティブでは JsError::new が panic するので、それもエラーとみなす）
+    fn rejects<T>(f: impl FnOnce() -> Result<T, JsError>) -> bool {
+        catch_unwind(AssertUnwindSafe(f)).map_or(true, |r| r.is_err())
+    }
+
+    #[test]
+    fn pixel_count_multiplies() {
+        assert_eq!(pixel_count(3, 2).ok(), Some(6));
+        assert_eq!(pixel_count(0, 100).ok(), Some(0));
+    }
+
+    #[test]
+    fn mask_len_must_match() {
+        assert!(check_mask_len("mask", &[0; 6], 3, 2).is_ok());
+        assert!(check_mask_len("mask", &[], 0, 0).is_ok());
+        assert!(rejects(|| check_mask_len("mask", &[0; 5], 3, 2)));
+        assert!(rejects(|| check_mask_len("mask", &[0; 24], 3, 2)));
+    }
+
+    #[test]
+    fn rgba_len_must_match() {
+        assert!(check_rgba_len("buffer", &[0; 24], 3, 2).is_ok());
+        assert!(rejects(|| check_rgba_len("buffer", &[0; 6], 3, 2)));
+        assert!(rejects(|| check_rgba_len("buffer", &[0; 23], 3, 2)));
+    }
+
+    #[test]
+    fn layers_len_returns_one_layer() {
+        assert_eq!(check_layers_len("layers", &[0; 48], 3, 2, 2).ok(), Some(24));
+        assert_eq!(check_layers_len("layers", &[], 3, 2, 0).ok(), Some(24));
+        assert!(rejects(|| check_layers_len("layers", &[0; 24], 3, 2, 2)));
+        assert!(rejects(|| check_layers_len(
+            "layers",
+            &[0; 24],
+            3,
+            2,
+            u32::MAX
+        )));
+    }
+
+    #[test]
+    fn same_len_compares_both() {
+        assert!(check_same_len("a", &[1, 2], "b", &[3, 4]).is_ok());
+        assert!(rejects(|| check_same_len("a", &[1, 2], "b", &[3])));
+    }
+
+    #[test]
+    fn finite_rejects_nan_and_infinity() {
+        assert!(check_finite("value", 0.5).is_ok());
+        assert!(check_finite("value", -1e30).is_ok());
+        for value in [f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+            assert!(rejects(|| check_finite("value", value)));
+        }
+    }
+}
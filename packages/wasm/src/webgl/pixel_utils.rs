@@ -1,9 +1,13 @@
 use wasm_bindgen::prelude::*;
 
+use crate::utils::validate::check_rgba_len;
+
 /// ピクセルデータを上下反転する関数
 /// WebGLのreadPixelsは下から上の順序で返すため、通常の画像として使う場合は反転が必要
 #[wasm_bindgen]
-pub fn flip_pixels_vertically(pixels: &mut [u8], width: u32, height: u32) {
+pub fn flip_pixels_vertically(pixels: &mut [u8], width: u32, height: u32) -> Result<(), JsError> {
+    check_rgba_len("pixels", pixels, width, height)?;
+
     let width = width as usize;
     let height = height as usize;
     let bytes_per_pixel = 4; // RGBA
@@ -19,4 +23,6 @@ pub fn flip_pixels_vertically(pixels: &mut [u8], width: u32, height: u32) {
             pixels.swap(top_row_start + x, bottom_row_start + x);
         }
     }
+
+    Ok(())
 }